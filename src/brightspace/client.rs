@@ -0,0 +1,276 @@
+use color_eyre::{
+    eyre::{eyre, Context, Report},
+    Result,
+};
+use http::Uri;
+use serde::de::DeserializeOwned;
+use url::form_urlencoded;
+
+use crate::brightspace::oauth::OAuthToken;
+use crate::models::{
+    BrightspaceObjectListPage, BrightspacePagedResultSet, BrightspaceProductVersions,
+};
+use crate::netid::NetidMapping;
+
+/// Oldest API versions coursectl is known to work with
+const MIN_LE_VERSION: &str = "1.67";
+const MIN_LP_VERSION: &str = "1.43";
+
+/// API versions used when the supported versions can't be retrieved
+const FALLBACK_LE_VERSION: &str = "1.72";
+const FALLBACK_LP_VERSION: &str = "1.45";
+
+/// The Valence product an API route belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Product {
+    /// Learning Environment: classlists, grades, dropboxes
+    Le,
+    /// Learning Platform: users, enrollments, groups
+    Lp,
+}
+
+impl Product {
+    fn code(self) -> &'static str {
+        match self {
+            Product::Le => "le",
+            Product::Lp => "lp",
+        }
+    }
+}
+
+/// Credentials used to authenticate requests
+#[derive(Debug)]
+pub enum Auth {
+    /// Browser session cookies
+    Cookie(String),
+    /// OAuth2 access token
+    OAuth(OAuthToken),
+}
+
+/// Client for the Brightspace (Valence) API
+///
+/// On creation the API versions are negotiated with the server,
+/// all urls are built using the newest version supported by both sides.
+#[derive(Debug)]
+pub struct Client {
+    base_url: Uri,
+    auth: Auth,
+    netids: NetidMapping,
+    le_version: String,
+    lp_version: String,
+}
+
+impl Client {
+    pub fn new(base_url: Uri, auth: Auth, netids: NetidMapping) -> Result<Self> {
+        let (le_version, lp_version) = match get_versions(&base_url) {
+            Ok(versions) => (
+                negotiate(&versions, Product::Le, MIN_LE_VERSION)?,
+                negotiate(&versions, Product::Lp, MIN_LP_VERSION)?,
+            ),
+            Err(e) => {
+                eprintln!("Failed retrieving brightspace api versions, using defaults: {e:#}");
+                (
+                    FALLBACK_LE_VERSION.to_string(),
+                    FALLBACK_LP_VERSION.to_string(),
+                )
+            }
+        };
+
+        Ok(Self {
+            base_url,
+            auth,
+            netids,
+            le_version,
+            lp_version,
+        })
+    }
+
+    /// Mapping used to determine the netids of users
+    pub fn netids(&self) -> &NetidMapping {
+        &self.netids
+    }
+
+    pub fn version(&self, product: Product) -> &str {
+        match product {
+            Product::Le => &self.le_version,
+            Product::Lp => &self.lp_version,
+        }
+    }
+
+    /// Builds the url of a versioned API route, `path` is the part after the version
+    pub fn url(&self, product: Product, path: &str) -> String {
+        format!(
+            "{}d2l/api/{}/{}/{path}",
+            self.base_url,
+            product.code(),
+            self.version(product)
+        )
+    }
+
+    /// Creates an authenticated request
+    fn request(&self, method: &str, url: &str) -> Result<ureq::Request> {
+        let req = ureq::request(method, url);
+        Ok(match &self.auth {
+            Auth::Cookie(cookie) => req.set("Cookie", cookie),
+            Auth::OAuth(token) => req.set(
+                "Authorization",
+                &format!("Bearer {}", token.access_token()?),
+            ),
+        })
+    }
+
+    /// Sends an authenticated request using `send`
+    ///
+    /// When brightspace rejects the OAuth2 access token it is refreshed and the request is sent again.
+    pub fn send(
+        &self,
+        method: &str,
+        url: &str,
+        send: impl Fn(ureq::Request) -> Result<ureq::Response, ureq::Error>,
+    ) -> Result<ureq::Response> {
+        let res = send(self.request(method, url)?);
+
+        if let (Err(ureq::Error::Status(401, _)), Auth::OAuth(token)) = (&res, &self.auth) {
+            token.refresh()?;
+            return Ok(send(self.request(method, url)?)?);
+        }
+
+        Ok(res?)
+    }
+
+    /// Performs an authenticated GET request on a versioned API route and parses the response
+    pub fn get<T: DeserializeOwned>(&self, product: Product, path: &str) -> Result<T> {
+        let res = self
+            .send("GET", &self.url(product, path), |req| req.call())?
+            .into_json()?;
+
+        Ok(res)
+    }
+
+    /// Retrieves all items of a route returning a paged result set, following the bookmarks
+    ///
+    /// See <https://docs.valence.desire2learn.com/basic/apicall.html#paged-data>
+    pub fn get_paged<T: DeserializeOwned>(&self, product: Product, path: &str) -> Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        let mut bookmark = String::new();
+
+        loop {
+            let page: BrightspacePagedResultSet<T> = self.get(
+                product,
+                &format!(
+                    "{path}{separator}bookmark={}",
+                    form_urlencoded::byte_serialize(bookmark.as_bytes()).collect::<String>()
+                ),
+            )?;
+            items.extend(page.items);
+
+            match page.paging_info.bookmark {
+                Some(next) if page.paging_info.has_more_items => bookmark = next,
+                _ => break,
+            }
+        }
+
+        Ok(items)
+    }
+
+    /// Retrieves all objects of a route returning object list pages, following the next links
+    ///
+    /// See <https://docs.valence.desire2learn.com/basic/apicall.html#object-list-pages>
+    pub fn get_object_list<T: DeserializeOwned>(
+        &self,
+        product: Product,
+        path: &str,
+    ) -> Result<Vec<T>> {
+        let mut objects = Vec::new();
+        let mut url = self.url(product, path);
+
+        loop {
+            let page: BrightspaceObjectListPage<T> =
+                self.send("GET", &url, |req| req.call())?.into_json()?;
+            objects.extend(page.objects);
+
+            match page.next {
+                Some(next) if next.starts_with('/') => {
+                    url = format!("{}{}", self.base_url, next.trim_start_matches('/'))
+                }
+                Some(next) => url = next,
+                None => break,
+            }
+        }
+
+        Ok(objects)
+    }
+}
+
+/// Whether `e` is caused by an error response with the given status code
+pub fn is_status(e: &Report, status: u16) -> bool {
+    matches!(e.downcast_ref::<ureq::Error>(), Some(ureq::Error::Status(s, _)) if *s == status)
+}
+
+/// See <https://docs.valence.desire2learn.com/res/apiprop.html#get--d2l-api-versions->
+fn get_versions(base_url: &Uri) -> Result<Vec<BrightspaceProductVersions>> {
+    let url = format!("{base_url}d2l/api/versions/");
+    let res = ureq::get(&url)
+        .call()?
+        .into_json()
+        .wrap_err("failed parsing api versions")?;
+
+    Ok(res)
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+    let (major, minor) = version.split_once('.')?;
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// Picks the newest version of a product supported by the server, which is at least `min`
+fn negotiate(
+    versions: &[BrightspaceProductVersions],
+    product: Product,
+    min: &str,
+) -> Result<String> {
+    let min = parse_version(min).expect("valid minimum version");
+
+    let supported = versions
+        .iter()
+        .find(|v| v.product_code == product.code())
+        .ok_or_else(|| eyre!("brightspace does not support the {} api", product.code()))?;
+
+    supported
+        .supported_versions
+        .iter()
+        .filter_map(|v| Some((parse_version(v)?, v)))
+        .filter(|(parsed, _)| *parsed >= min)
+        .max_by_key(|(parsed, _)| *parsed)
+        .map(|(_, v)| v.clone())
+        .ok_or_else(|| {
+            eyre!(
+                "brightspace does not support {} api version {}.{} or newer",
+                product.code(),
+                min.0,
+                min.1
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{negotiate, Product};
+    use crate::models::BrightspaceProductVersions;
+
+    #[test]
+    fn picks_newest_supported_version() {
+        let versions: Vec<BrightspaceProductVersions> = serde_json::from_str(
+            r#"[
+                {"ProductCode": "le", "LatestVersion": "1.78", "SupportedVersions": ["1.9", "1.66", "1.72", "1.78"]},
+                {"ProductCode": "lp", "LatestVersion": "1.46", "SupportedVersions": ["1.40", "1.45", "1.46"]}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(negotiate(&versions, Product::Le, "1.67").unwrap(), "1.78");
+        assert_eq!(negotiate(&versions, Product::Lp, "1.43").unwrap(), "1.46");
+        assert!(negotiate(&versions, Product::Lp, "1.50").is_err());
+    }
+}
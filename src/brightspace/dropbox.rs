@@ -0,0 +1,168 @@
+use std::{fs, io, path::Path};
+
+use color_eyre::{eyre::Context, Result};
+use indicatif::ProgressIterator;
+
+use super::{client::Product, Client};
+use crate::failures::Failures;
+use crate::models::{BrightspaceDropboxFolder, BrightspaceEntityDropbox};
+
+impl Client {
+    /// Lists the dropbox folders of a course
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/dropbox.html#get--d2l-api-le-(version)-(orgUnitId)-dropbox-folders->
+    pub fn get_dropbox_folders(&self, ou: u64) -> Result<Vec<BrightspaceDropboxFolder>> {
+        self.get(Product::Le, &format!("{ou}/dropbox/folders/"))
+    }
+
+    /// Lists the submissions of every user (or group) in a dropbox folder
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/dropbox.html#get--d2l-api-le-(version)-(orgUnitId)-dropbox-folders-(folderId)-submissions->
+    pub fn get_dropbox_submissions(
+        &self,
+        ou: u64,
+        folder: u64,
+    ) -> Result<Vec<BrightspaceEntityDropbox>> {
+        self.get(
+            Product::Le,
+            &format!("{ou}/dropbox/folders/{folder}/submissions/"),
+        )
+    }
+
+    /// Downloads a single file of a submission to `target`
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/dropbox.html#get--d2l-api-le-(version)-(orgUnitId)-dropbox-folders-(folderId)-submissions-(submissionId)-files-(fileId)>
+    fn download_dropbox_file(
+        &self,
+        ou: u64,
+        folder: u64,
+        submission: u64,
+        file: u64,
+        target: &Path,
+    ) -> Result<()> {
+        let url = self.url(
+            Product::Le,
+            &format!("{ou}/dropbox/folders/{folder}/submissions/{submission}/files/{file}"),
+        );
+        let res = self.send("GET", &url, |req| req.call())?;
+
+        let mut f = fs::File::create(target)
+            .wrap_err_with(|| format!("could not create {}", target.display()))?;
+        io::copy(&mut res.into_reader(), &mut f)?;
+
+        Ok(())
+    }
+}
+
+/// Replaces characters which can't be used in file names
+///
+/// Names like `.` and `..`, which would refer to another directory, have their dots replaced too.
+fn sanitize(name: &str) -> String {
+    if name.chars().all(|c| c == '.') {
+        return "_".repeat(name.len().max(1));
+    }
+
+    name.chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Downloads the latest submission of every student in the given dropbox folders
+/// (or all folders) to `<out>/<netid>/<folder>/`
+///
+/// Group submissions, and students who are no longer in the classlist, use their display name instead of a netid.
+pub fn download_submissions(
+    client: &Client,
+    ou: u64,
+    folders: &[u64],
+    out: &Path,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let netids = client.get_netids_by_identifier(ou)?;
+
+    let folders: Vec<BrightspaceDropboxFolder> = client
+        .get_dropbox_folders(ou)
+        .wrap_err("failed getting dropbox folders from brightspace")?
+        .into_iter()
+        .filter(|f| folders.is_empty() || folders.contains(&f.id))
+        .collect();
+
+    let mut n = 0;
+
+    for folder in &folders {
+        println!("Downloading submissions of {}", folder.name);
+
+        let submissions = client
+            .get_dropbox_submissions(ou, folder.id)
+            .wrap_err_with(|| format!("failed getting submissions of {}", folder.name))?;
+
+        for entity in submissions.iter().progress() {
+            let Some(latest) = entity.submissions.iter().max_by_key(|s| s.submission_date) else {
+                continue;
+            };
+
+            let name = netids
+                .get(&entity.entity.entity_id.to_string())
+                .cloned()
+                .unwrap_or_else(|| entity.entity.display_name.clone());
+            if !failures.selected(&name) {
+                continue;
+            }
+            let dir = out.join(sanitize(&name)).join(sanitize(&folder.name));
+
+            for file in &latest.files {
+                let target = dir.join(sanitize(&file.file_name));
+
+                if dry_run {
+                    println!("Dry Run: downloading {}", target.display());
+                    continue;
+                }
+
+                let res = fs::create_dir_all(&dir)
+                    .wrap_err("could not create output directory")
+                    .and_then(|()| {
+                        client.download_dropbox_file(
+                            ou,
+                            folder.id,
+                            latest.id,
+                            file.file_id,
+                            &target,
+                        )
+                    });
+
+                match res {
+                    Ok(()) => n += 1,
+                    Err(e) => failures.push(
+                        &name,
+                        e.wrap_err(format!("failed downloading {}", file.file_name)),
+                    ),
+                }
+            }
+        }
+    }
+
+    println!(
+        "Downloaded {n} files from {} dropbox folders.",
+        folders.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sanitize;
+
+    #[test]
+    fn sanitizes_file_names() {
+        assert_eq!(sanitize("report: v1/final.pdf"), "report_ v1_final.pdf");
+        assert_eq!(sanitize(".."), "__");
+        assert_eq!(sanitize("."), "_");
+        assert_eq!(sanitize(""), "_");
+        assert_eq!(sanitize("..hidden"), "..hidden");
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::{BTreeMap, HashMap};
+
+use color_eyre::{eyre::Context, Result};
+
+use super::{
+    client::{is_status, Product},
+    Client,
+};
+use crate::models::{BrightspaceOrgUnitUser, BrightspaceSection, BrightspaceUser, Enrollment};
+
+/// Role id of students in the TU Delft brightspace
+pub const STUDENT_ROLE_ID: u64 = 110;
+
+/// A role occurring in a course, see [`Client::get_roles`]
+#[derive(Debug)]
+pub struct CourseRole {
+    pub id: u64,
+    pub name: String,
+    pub display_name: Option<String>,
+    /// Number of users enrolled with this role
+    pub users: usize,
+}
+
+impl Client {
+    /// Lists the sections of a course, courses without sections return an empty list
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/enroll.html#get--d2l-api-lp-(version)-(orgUnitId)-sections->
+    pub fn get_sections(&self, ou: u64) -> Result<Vec<BrightspaceSection>> {
+        let url = self.url(Product::Lp, &format!("{ou}/sections/"));
+
+        match self.send("GET", &url, |req| req.call()) {
+            Ok(res) => Ok(res.into_json()?),
+            Err(e) if is_status(&e, 404) => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Retrieves everyone enrolled in a course, together with the sections they are in
+    pub fn get_enrollments(&self, ou: u64) -> Result<Vec<Enrollment>> {
+        Ok(self
+            .get_enrolled_users(ou)?
+            .into_iter()
+            .map(|(enrollment, _)| enrollment)
+            .collect())
+    }
+
+    /// Like [`Client::get_enrollments`], but also returns the user data of everyone enrolled
+    ///
+    /// The enrollments are retrieved in pages, which also works for very large courses.
+    /// See <https://docs.valence.desire2learn.com/res/enroll.html#get--d2l-api-lp-(version)-enrollments-orgUnits-(orgUnitId)-users->
+    pub fn get_enrolled_users(&self, ou: u64) -> Result<Vec<(Enrollment, BrightspaceUser)>> {
+        let users: Vec<BrightspaceOrgUnitUser> = self
+            .get_paged(Product::Lp, &format!("enrollments/orgUnits/{ou}/users/"))
+            .wrap_err("failed getting enrollments from brightspace")?;
+
+        let mut sections: HashMap<String, Vec<String>> = HashMap::new();
+        for section in self
+            .get_sections(ou)
+            .wrap_err("failed getting sections from brightspace")?
+        {
+            for user in section.enrollments {
+                sections
+                    .entry(user.to_string())
+                    .or_default()
+                    .push(section.name.clone());
+            }
+        }
+
+        Ok(users
+            .into_iter()
+            .map(|u| {
+                let enrollment = Enrollment {
+                    sections: sections.remove(&u.user.identifier).unwrap_or_default(),
+                    display_name: u.user.display_name.clone().unwrap_or_default(),
+                    identifier: u.user.identifier.clone(),
+                    role_id: u.role.id,
+                    role_name: u.role.name,
+                    enrollment_date: u.enrollment_date,
+                };
+                (enrollment, u.user)
+            })
+            .collect())
+    }
+
+    /// Lists the roles of everyone enrolled in a course
+    pub fn get_roles(&self, ou: u64) -> Result<Vec<CourseRole>> {
+        let display_names: HashMap<u64, String> = self
+            .get_classlist(ou)
+            .wrap_err("failed getting classlist from brightspace")?
+            .into_iter()
+            .filter_map(|e| Some((e.role_id?, e.classlist_role_display_name)))
+            .collect();
+
+        let mut roles = BTreeMap::new();
+        for enrollment in self.get_enrollments(ou)? {
+            roles
+                .entry(enrollment.role_id)
+                .or_insert_with(|| CourseRole {
+                    id: enrollment.role_id,
+                    name: enrollment.role_name.clone(),
+                    display_name: display_names.get(&enrollment.role_id).cloned(),
+                    users: 0,
+                })
+                .users += 1;
+        }
+
+        Ok(roles.into_values().collect())
+    }
+}
@@ -0,0 +1,94 @@
+use color_eyre::{eyre::Context, Result};
+
+use super::{
+    client::{is_status, Product},
+    Client,
+};
+use crate::models::{
+    BrightspaceGradeObject, BrightspaceGradeValue, BrightspaceIncomingGradeValue,
+    BrightspaceRichText, BrightspaceRichTextInput,
+};
+
+/// Grade object type of numeric grade items
+const NUMERIC_GRADE_OBJECT: u64 = 1;
+
+impl Client {
+    /// Returns the grade of a user for a grade object, or `None` if they have not been graded yet
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/grade.html#get--d2l-api-le-(version)-(orgUnitId)-grades-(gradeObjectId)-values-(userId)>
+    pub fn get_grade_value(
+        &self,
+        ou: u64,
+        grade_object: u64,
+        user: &str,
+    ) -> Result<Option<BrightspaceGradeValue>> {
+        let url = self.url(
+            Product::Le,
+            &format!("{ou}/grades/{grade_object}/values/{user}"),
+        );
+
+        match self.send("GET", &url, |req| req.call()) {
+            Ok(res) => Ok(Some(res.into_json()?)),
+            Err(e) if is_status(&e, 404) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the grade of a user for a numeric grade object
+    ///
+    /// The grade value is replaced as a whole, so the current comments are kept when `feedback` is
+    /// `None`, and the private comments are always kept.
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/grade.html#put--d2l-api-le-(version)-(orgUnitId)-grades-(gradeObjectId)-values-(userId)>
+    pub fn put_grade_value(
+        &self,
+        ou: u64,
+        grade_object: u64,
+        user: &str,
+        score: f64,
+        feedback: Option<&str>,
+    ) -> Result<()> {
+        let url = self.url(
+            Product::Le,
+            &format!("{ou}/grades/{grade_object}/values/{user}"),
+        );
+
+        let current = self
+            .get_grade_value(ou, grade_object, user)
+            .wrap_err("failed getting current grade value")?;
+        let current_comments = |comments: Option<&BrightspaceRichText>| {
+            comments.map_or_else(|| BrightspaceRichTextInput::text(""), Into::into)
+        };
+
+        let value = BrightspaceIncomingGradeValue {
+            comments: match feedback {
+                Some(feedback) => BrightspaceRichTextInput::text(feedback),
+                None => current_comments(current.as_ref().and_then(|v| v.comments.as_ref())),
+            },
+            private_comments: current_comments(
+                current.as_ref().and_then(|v| v.private_comments.as_ref()),
+            ),
+            grade_object_type: NUMERIC_GRADE_OBJECT,
+            points_numerator: score,
+        };
+
+        self.send("PUT", &url, |req| req.send_json(&value))
+            .wrap_err("failed setting grade value")?;
+
+        Ok(())
+    }
+
+    /// Lists the grade objects of a course
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/grade.html#get--d2l-api-le-(version)-(orgUnitId)-grades->
+    pub fn get_grade_objects(&self, ou: u64) -> Result<Vec<BrightspaceGradeObject>> {
+        self.get(Product::Le, &format!("{ou}/grades/"))
+    }
+
+    /// Returns all grades of a single user in a course
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/grade.html#get--d2l-api-le-(version)-(orgUnitId)-grades-values-(userId)->
+    pub fn get_user_grade_values(&self, ou: u64, user: &str) -> Result<Vec<BrightspaceGradeValue>> {
+        self.get(Product::Le, &format!("{ou}/grades/values/{user}/"))
+    }
+}
@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+
+use crate::lms::{Role, StudentFilter};
+use crate::models::{
+    BrightspaceClassList, BrightspaceClassListEntry, BrightspaceGroupCategory,
+    BrightspaceGroupData, BrightspaceGroupRecord, Enrollment, Group, Student,
+};
+use crate::netid::{self, NetidMapping};
+use color_eyre::{
+    eyre::{bail, Context},
+    Result,
+};
+use itertools::Itertools;
+
+pub mod client;
+pub mod dropbox;
+pub mod enrollments;
+pub mod grades;
+pub mod oauth;
+
+use client::Product;
+pub use client::{Auth, Client};
+
+impl Client {
+    /// Retrieves the classlist of a course in pages
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/enroll.html#get--d2l-api-le-(version)-(orgUnitId)-classlist-paged->
+    pub fn get_classlist(&self, ou: u64) -> Result<BrightspaceClassList> {
+        self.get_object_list(Product::Le, &format!("{ou}/classlist/paged/"))
+    }
+
+    pub fn get_students(&self, ou: u64, filter: &StudentFilter) -> Result<Vec<Student>> {
+        Ok(self
+            .get_students_by_identifier(ou, filter)?
+            .into_iter()
+            .map(|(_, s)| s)
+            .collect())
+    }
+
+    /// Like [`Client::get_students`], but also returns the brightspace user identifier of every student
+    pub fn get_students_by_identifier(
+        &self,
+        ou: u64,
+        filter: &StudentFilter,
+    ) -> Result<Vec<(String, Student)>> {
+        Ok(self
+            .get_enrolled_students(ou, filter)?
+            .into_iter()
+            .map(|(e, s)| (e.identifier, s))
+            .collect())
+    }
+
+    /// Like [`Client::get_students`], but also returns the enrollment of every student
+    ///
+    /// Students are built from the enrollments, only their usernames (and the display names of
+    /// their roles) are taken from the classlist, as the enrollments API does not return those.
+    pub fn get_enrolled_students(
+        &self,
+        ou: u64,
+        filter: &StudentFilter,
+    ) -> Result<Vec<(Enrollment, Student)>> {
+        let filter = filter.or_roles(vec![Role::Id(enrollments::STUDENT_ROLE_ID)]);
+        let users = self.get_enrolled_users(ou)?;
+
+        if filter.enrolled_after.is_some() && users.iter().any(|(e, _)| e.enrollment_date.is_none())
+        {
+            bail!("brightspace did not return enrollment dates, can't filter on them");
+        }
+
+        let mut classlist: HashMap<String, BrightspaceClassListEntry> = self
+            .get_classlist(ou)
+            .wrap_err("failed getting classlist from brightspace")?
+            .into_iter()
+            .map(|e| (e.identifier.clone(), e))
+            .collect();
+
+        let mut students = Vec::new();
+        let mut unmapped = Vec::new();
+        for (e, user) in users {
+            let entry = classlist.remove(&e.identifier);
+            let role_display_name = entry
+                .as_ref()
+                .map_or("", |entry| entry.classlist_role_display_name.as_str());
+            if !filter.matches(&e, role_display_name) {
+                continue;
+            }
+
+            let username = entry.as_ref().and_then(|entry| entry.username.as_deref());
+            match user.to_student(username, self.netids()) {
+                Some(student) => students.push((e, student)),
+                None => unmapped.push(format!(
+                    "{} ({})",
+                    e.display_name,
+                    username.unwrap_or("no username")
+                )),
+            }
+        }
+
+        netid::skip_unmapped(unmapped);
+        Ok(students)
+    }
+
+    /// Maps the brightspace user identifiers of everyone in the classlist to their netid
+    pub fn get_netids_by_identifier(&self, ou: u64) -> Result<HashMap<String, String>> {
+        let classlist = self
+            .get_classlist(ou)
+            .wrap_err("failed getting classlist from brightspace")?;
+
+        Ok(classlist
+            .into_iter()
+            .filter_map(|e| {
+                let student = e.to_student(self.netids())?;
+                Some((e.identifier, student.netid))
+            })
+            .collect())
+    }
+
+    /// Lists the group categories of a course
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/groups.html#get--d2l-api-lp-(version)-(orgUnitId)-groupcategories->
+    pub fn get_group_categories(&self, ou: u64) -> Result<Vec<BrightspaceGroupCategory>> {
+        self.get(Product::Lp, &format!("{ou}/groupcategories/"))
+    }
+
+    /// Retrieves all groups in a group category, with their members resolved through the classlist
+    ///
+    /// See <https://docs.valence.desire2learn.com/res/groups.html#get--d2l-api-lp-(version)-(orgUnitId)-groupcategories-(groupCategoryId)-groups->
+    pub fn get_groups(&self, ou: u64, category: u64) -> Result<Vec<Group>> {
+        let groups: Vec<BrightspaceGroupData> = self
+            .get(
+                Product::Lp,
+                &format!("{ou}/groupcategories/{category}/groups/"),
+            )
+            .wrap_err("failed getting groups from brightspace")?;
+
+        let students: HashMap<String, Student> = self
+            .get_students_by_identifier(ou, &StudentFilter::default())?
+            .into_iter()
+            .collect();
+
+        Ok(groups
+            .into_iter()
+            .map(|g| {
+                let members = g
+                    .enrollments
+                    .iter()
+                    .filter_map(|id| {
+                        let student = students.get(&id.to_string()).cloned();
+                        if student.is_none() {
+                            eprintln!(
+                                "Skipping user {id} in {}, not a student in the classlist",
+                                g.name
+                            );
+                        }
+                        student
+                    })
+                    .collect();
+
+                Group {
+                    name: g.name.replace(' ', ""),
+                    members,
+                }
+            })
+            .collect())
+    }
+}
+
+const GROUP_EXPORT_URL: &str = "https://group-impexp.lti.tudelft.nl/export/";
+
+/// Retrieves all groups in a group category through the group-impexp LTI tool
+///
+/// Prefer [`Client::get_groups`], which uses the normal brightspace API.
+pub fn get_groups_lti(
+    sessionid: &str,
+    category: &str,
+    netids: &NetidMapping,
+) -> Result<Vec<Group>> {
+    let res = ureq::post(GROUP_EXPORT_URL)
+        .set("Cookie", &format!("sessionid={sessionid}"))
+        .send_form(&[("resource_link_id", "2116724775"), ("categories", category)])?;
+
+    let s = res
+        .into_string()?
+        .lines()
+        .filter(|line| !line.starts_with(','))
+        .join("\n");
+
+    let mut reader = csv::Reader::from_reader(s.as_bytes());
+
+    let mut hm: HashMap<String, Vec<Student>> = HashMap::new();
+    let mut unmapped = Vec::new();
+
+    for row in reader.deserialize() {
+        let student: BrightspaceGroupRecord = row?;
+        let group_name = student.group_name.replace(' ', "");
+
+        let Some(s) = student.to_student(netids) else {
+            unmapped.push(format!("{} ({group_name})", student.username));
+            continue;
+        };
+
+        hm.entry(group_name)
+            .and_modify(|e| e.push(s.clone()))
+            .or_insert_with(|| vec![s]);
+    }
+
+    netid::skip_unmapped(unmapped);
+    Ok(Group::from_hm(hm))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::BrightspaceClassList;
+
+    #[test]
+    pub fn try_parse() {
+        let data = r#"
+        [{
+    "Identifier": "1",
+    "ProfileIdentifier": "p1",
+    "DisplayName": "Doe, John",
+    "Username": "jdoe@tudelft.nl",
+    "OrgDefinedId": "12345",
+    "Email": "J.Doe@tudelft.nl",
+    "FirstName": "John",
+    "LastName": "Doe",
+    "RoleId": 110,
+    "LastAccessed": "2023-08-23T8:52:20.032Z",
+    "IsOnline": false,
+    "ClasslistRoleDisplayName": "Student"
+  }]
+        "#;
+        let _: BrightspaceClassList = serde_json::from_str(data).unwrap();
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    env, fs,
+    io::{BufRead, BufReader, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::ValueEnum;
+use color_eyre::{
+    eyre::{bail, eyre, Context, ContextCompat},
+    Result,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+const AUTH_URL: &str = "https://auth.brightspace.com/oauth2/auth";
+const TOKEN_URL: &str = "https://auth.brightspace.com/core/connect/token";
+
+/// Tokens expiring within this many seconds are refreshed before use
+const EXPIRY_MARGIN: u64 = 60;
+
+/// How to authenticate against brightspace
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AuthMode {
+    /// OAuth2 if a client id is configured, otherwise browser cookies
+    Auto,
+    /// Session cookies taken from the browser
+    Cookie,
+    /// OAuth2 authorization code flow
+    Oauth,
+}
+
+/// Settings of a registered brightspace OAuth2 application
+#[derive(Debug)]
+pub struct OAuthConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub scope: String,
+    pub cache: PathBuf,
+}
+
+/// OAuth2 access token, which is refreshed when it is about to expire or gets rejected
+#[derive(Debug)]
+pub struct OAuthToken {
+    config: OAuthConfig,
+    token: Mutex<TokenCache>,
+}
+
+impl OAuthToken {
+    /// Loads a valid token
+    ///
+    /// The cached token is used if it is still valid, otherwise it is refreshed.
+    /// When there is no (refreshable) token the user is asked to authorize coursectl in their browser.
+    pub fn new(config: OAuthConfig) -> Result<Self> {
+        let cached = fs::read_to_string(&config.cache)
+            .ok()
+            .and_then(|s| serde_json::from_str::<TokenCache>(&s).ok());
+
+        let token = match cached {
+            Some(cached) if !cached.expires_soon() => cached,
+            Some(cached) => renew(&config, &cached)?,
+            None => {
+                let token = authorize(&config)?;
+                save(&config.cache, &token)?;
+                token
+            }
+        };
+
+        Ok(Self {
+            config,
+            token: Mutex::new(token),
+        })
+    }
+
+    /// Returns the access token, refreshing it first if it is about to expire
+    pub fn access_token(&self) -> Result<String> {
+        let mut token = self.token.lock().unwrap();
+        if token.expires_soon() {
+            *token = renew(&self.config, &token)?;
+        }
+
+        Ok(token.access_token.clone())
+    }
+
+    /// Replaces the access token, after brightspace rejected it
+    pub fn refresh(&self) -> Result<()> {
+        let mut token = self.token.lock().unwrap();
+        *token = renew(&self.config, &token)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TokenCache {
+    access_token: String,
+    refresh_token: Option<String>,
+    /// Unix timestamp in seconds
+    expires_at: u64,
+}
+
+/// See <https://docs.valence.desire2learn.com/basic/oauth2.html>
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: u64,
+}
+
+impl TokenCache {
+    fn expires_soon(&self) -> bool {
+        self.expires_at <= now() + EXPIRY_MARGIN
+    }
+}
+
+impl From<TokenResponse> for TokenCache {
+    fn from(res: TokenResponse) -> Self {
+        Self {
+            access_token: res.access_token,
+            refresh_token: res.refresh_token,
+            expires_at: now() + res.expires_in,
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Default location of the token cache, inside of the XDG cache directory
+pub fn default_token_cache() -> PathBuf {
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .unwrap_or_else(env::temp_dir)
+        .join("coursectl")
+        .join("brightspace-token.json")
+}
+
+/// Refreshes `token`, or asks the user to authorize coursectl again if that is not possible
+fn renew(config: &OAuthConfig, token: &TokenCache) -> Result<TokenCache> {
+    let renewed = match &token.refresh_token {
+        Some(refresh_token) => match refresh(config, refresh_token) {
+            Ok(mut renewed) => {
+                // the refresh token is only returned when it changed
+                if renewed.refresh_token.is_none() {
+                    renewed.refresh_token = Some(refresh_token.clone());
+                }
+                Some(renewed)
+            }
+            Err(e) => {
+                eprintln!("Failed refreshing brightspace token: {e:#}");
+                None
+            }
+        },
+        None => None,
+    };
+
+    let renewed = match renewed {
+        Some(renewed) => renewed,
+        None => authorize(config)?,
+    };
+
+    save(&config.cache, &renewed)?;
+    Ok(renewed)
+}
+
+fn save(path: &Path, token: &TokenCache) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    options
+        .open(path)
+        .and_then(|mut f| f.write_all(serde_json::to_string(token)?.as_bytes()))
+        .wrap_err_with(|| format!("failed writing token cache {}", path.display()))
+}
+
+fn refresh(config: &OAuthConfig, refresh_token: &str) -> Result<TokenCache> {
+    let res: TokenResponse = ureq::post(TOKEN_URL)
+        .send_form(&[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("scope", config.scope.as_str()),
+        ])?
+        .into_json()?;
+
+    Ok(res.into())
+}
+
+/// Runs the authorization code flow, receiving the code on a local listener at the redirect uri
+fn authorize(config: &OAuthConfig) -> Result<TokenCache> {
+    let redirect = Url::parse(&config.redirect_uri).wrap_err("invalid redirect uri")?;
+    let addr = format!(
+        "{}:{}",
+        redirect.host_str().wrap_err("redirect uri has no host")?,
+        redirect.port_or_known_default().unwrap_or(80)
+    );
+
+    let state: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+
+    let auth_url = Url::parse_with_params(
+        AUTH_URL,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("scope", config.scope.as_str()),
+            ("state", state.as_str()),
+        ],
+    )?;
+
+    let listener =
+        TcpListener::bind(&addr).wrap_err_with(|| format!("failed listening on {addr}"))?;
+
+    eprintln!("Open the following url in your browser to log in to brightspace:\n{auth_url}");
+
+    let (stream, _) = listener.accept()?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    // GET /callback?code=...&state=... HTTP/1.1
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .wrap_err("invalid request on redirect uri")?;
+    let callback = redirect.join(path)?;
+
+    let param = |name: &str| {
+        callback
+            .query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    };
+
+    let body = "You can close this window and return to coursectl.";
+    write!(
+        &stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+
+    if let Some(error) = param("error") {
+        bail!("authorization failed: {error}");
+    }
+    if param("state").as_deref() != Some(state.as_str()) {
+        bail!("authorization failed: state mismatch");
+    }
+    let code = param("code").ok_or_else(|| eyre!("authorization failed: no code received"))?;
+
+    let res: TokenResponse = ureq::post(TOKEN_URL)
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+        ])?
+        .into_json()?;
+
+    Ok(res.into())
+}
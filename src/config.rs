@@ -0,0 +1,292 @@
+use std::{collections::HashMap, env, fs, path::PathBuf};
+
+use clap::Command;
+use color_eyre::eyre::{eyre, Context, Result};
+use itertools::Itertools;
+use serde::Deserialize;
+
+const CONFIG_FILE_NAME: &str = "coursectl.toml";
+
+/// Contents of a `coursectl.toml` file
+///
+/// ```toml
+/// default = "rts"
+///
+/// [courses.rts]
+/// host = "gitlab.ewi.tudelft.nl"
+/// group_id = 30014
+/// ou = 594625
+/// template = "https://gitlab.ewi.tudelft.nl/cese/rts/template.git"
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Course to use when none is selected with `--course`
+    pub default: Option<String>,
+
+    #[serde(default)]
+    pub courses: HashMap<String, Course>,
+}
+
+/// A named course, every field is used as the default of the matching command line argument(s)
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Course {
+    /// Gitlab host url
+    pub host: Option<String>,
+    /// Learning management system, `brightspace` or `canvas`
+    pub lms: Option<String>,
+    /// Brightspace base url
+    pub brightspace_url: Option<String>,
+    /// Canvas instance url
+    pub canvas_url: Option<String>,
+    /// Gitlab group containing the student repositories
+    pub group_id: Option<u64>,
+    /// Brightspace Organizational Unit ID
+    pub ou: Option<u64>,
+    /// Brightspace group category
+    pub group_category: Option<u64>,
+    /// Template repository url
+    pub template: Option<String>,
+    /// Prefix of individual repositories
+    pub prefix: Option<String>,
+    /// Access level given to students
+    pub access_level: Option<u64>,
+    /// Domains stripped from brightspace usernames to get the netid
+    pub netid_domains: Option<Vec<String>>,
+    /// Regex extracting the netid from brightspace usernames
+    pub netid_pattern: Option<String>,
+    /// Fall back to the local part of the email address for the netid
+    pub netid_email_fallback: Option<bool>,
+}
+
+impl Config {
+    /// Loads `coursectl.toml` from the current directory, or otherwise from the XDG config directory.
+    ///
+    /// Returns an empty config if neither exists.
+    pub fn load() -> Result<Self> {
+        let Some(path) = Self::path() else {
+            return Ok(Self::default());
+        };
+
+        let contents = fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed reading {}", path.display()))?;
+
+        toml::from_str(&contents).wrap_err_with(|| format!("failed parsing {}", path.display()))
+    }
+
+    fn path() -> Option<PathBuf> {
+        let local = PathBuf::from(CONFIG_FILE_NAME);
+        if local.is_file() {
+            return Some(local);
+        }
+
+        let config_dir = env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+
+        let global = config_dir.join("coursectl").join(CONFIG_FILE_NAME);
+        global.is_file().then_some(global)
+    }
+
+    /// Returns the course with the given name, or the default course if no name is given
+    pub fn course(&self, name: Option<&str>) -> Result<Option<&Course>> {
+        let Some(name) = name.or(self.default.as_deref()) else {
+            return Ok(None);
+        };
+
+        self.courses.get(name).map(Some).ok_or_else(|| {
+            eyre!(
+                "unknown course {name}, available courses: {}",
+                self.courses.keys().sorted().join(", ")
+            )
+        })
+    }
+}
+
+impl Course {
+    /// Maps the fields of the course to the ids of the arguments they provide a default for
+    fn defaults(&self) -> Vec<(&'static str, String)> {
+        let mut defaults = Vec::new();
+
+        let mut add = |ids: &[&'static str], value: Option<String>| {
+            if let Some(value) = value {
+                defaults.extend(ids.iter().map(|&id| (id, value.clone())));
+            }
+        };
+
+        add(&["host"], self.host.clone());
+        add(&["lms"], self.lms.clone());
+        add(&["base_url"], self.brightspace_url.clone());
+        add(&["canvas_url"], self.canvas_url.clone());
+        add(
+            &["group_id", "gitlab_group_id"],
+            self.group_id.map(|id| id.to_string()),
+        );
+        add(
+            &["brightspace_ou", "course_id"],
+            self.ou.map(|ou| ou.to_string()),
+        );
+        add(
+            &["brightspace_group_id"],
+            self.group_category.map(|c| c.to_string()),
+        );
+        add(&["template_repository"], self.template.clone());
+        add(&["repo_name_prefix"], self.prefix.clone());
+        add(&["access_level"], self.access_level.map(|l| l.to_string()));
+        add(
+            &["netid_domains"],
+            self.netid_domains.as_ref().map(|d| d.join(",")),
+        );
+        add(&["netid_pattern"], self.netid_pattern.clone());
+        add(
+            &["netid_email_fallback"],
+            self.netid_email_fallback.map(|b| b.to_string()),
+        );
+
+        defaults
+    }
+
+    /// Sets the values of this course as the defaults of the matching arguments of every subcommand,
+    /// which makes those arguments optional.
+    ///
+    /// Positional arguments followed by a required positional argument are left alone, an optional
+    /// positional argument can't come before a required one.
+    pub fn apply(&self, mut cmd: Command) -> Command {
+        let defaults = self.defaults();
+
+        let subcommands = cmd
+            .get_subcommands()
+            .map(|sc| sc.get_name().to_string())
+            .collect_vec();
+
+        for name in subcommands {
+            cmd = cmd.mut_subcommand(name, |mut sc| {
+                for (id, value) in &defaults {
+                    if sc.get_arguments().any(|a| a.get_id() == *id)
+                        && !precedes_required_positional(&sc, id)
+                    {
+                        sc = sc.mut_arg(*id, |a| a.default_value(value.clone()).required(false));
+                    }
+                }
+                sc
+            });
+        }
+
+        cmd
+    }
+}
+
+/// Whether the argument `id` is positional and followed by a required positional argument
+fn precedes_required_positional(cmd: &Command, id: &str) -> bool {
+    cmd.get_positionals()
+        .skip_while(|a| a.get_id() != id)
+        .skip(1)
+        .any(|a| a.is_required_set())
+}
+
+/// Finds the course selected with `--course`, before the rest of the arguments are parsed
+///
+/// The arguments are parsed leniently, required arguments may still be missing at this point
+/// because the course provides their defaults.
+pub fn selected_course(cmd: &Command) -> Option<String> {
+    let matches = cmd.clone().ignore_errors(true).try_get_matches().ok()?;
+    matches.get_one::<String>("course").cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+
+    use super::Config;
+    use crate::{Cli, Commands};
+
+    const CONFIG: &str = r#"
+default = "rts"
+
+[courses.rts]
+host = "gitlab.example.com"
+group_id = 30014
+ou = 594625
+template = "https://gitlab.example.com/rts/template.git"
+prefix = "RTS"
+"#;
+
+    #[test]
+    fn course_provides_defaults() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let course = config.course(None).unwrap().unwrap();
+
+        let matches = course
+            .apply(Cli::command())
+            .try_get_matches_from([
+                "coursectl",
+                "create-individual-repos",
+                "--user",
+                "u",
+                "--token",
+                "t",
+                "--cookie",
+                "c",
+                "--session-id",
+                "s",
+            ])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+
+        let Commands::CreateIndividualRepos {
+            brightspace_ou,
+            gitlab,
+            project,
+            repo_name_prefix,
+            ..
+        } = cli.command
+        else {
+            panic!("wrong subcommand");
+        };
+
+        assert_eq!(brightspace_ou, Some(594625));
+        assert_eq!(gitlab.host, "gitlab.example.com");
+        assert_eq!(project.gitlab_group_id, 30014);
+        assert_eq!(repo_name_prefix.as_deref(), Some("RTS"));
+    }
+
+    #[test]
+    fn course_keeps_positionals_valid() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        let course = config.course(None).unwrap().unwrap();
+
+        let cmd = course.apply(Cli::command());
+        cmd.clone().debug_assert();
+
+        let matches = cmd
+            .try_get_matches_from([
+                "coursectl",
+                "collect",
+                "--user",
+                "u",
+                "--token",
+                "t",
+                "42",
+                "*.pdf",
+            ])
+            .unwrap();
+        let cli = Cli::from_arg_matches(&matches).unwrap();
+
+        let Commands::Collect {
+            group_id, patterns, ..
+        } = cli.command
+        else {
+            panic!("wrong subcommand");
+        };
+
+        assert_eq!(group_id, 42);
+        assert_eq!(patterns, ["*.pdf"]);
+    }
+
+    #[test]
+    fn unknown_course() {
+        let config: Config = toml::from_str(CONFIG).unwrap();
+        assert!(config.course(Some("nope")).is_err());
+    }
+}
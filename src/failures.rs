@@ -0,0 +1,152 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use color_eyre::eyre::{bail, Context, Report, Result};
+use serde::{Deserialize, Serialize};
+
+/// Row of the failures file
+#[derive(Debug, Serialize, Deserialize)]
+struct FailureRecord {
+    item: String,
+    error: String,
+}
+
+/// Collects the errors of a bulk operation per project or student
+///
+/// Operations report errors through [`Failures::check`], which returns the error right away unless
+/// `--keep-going` was given, or through [`Failures::push`] when they always continue with the next
+/// project. Everything that failed is reported once by [`Failures::finish`].
+#[derive(Debug, Default)]
+pub struct Failures {
+    keep_going: bool,
+    retry: Option<HashSet<String>>,
+    failed: Vec<(String, Report)>,
+}
+
+impl Failures {
+    /// With `retry_from` only the items listed in that failures file are processed
+    pub fn new(keep_going: bool, retry_from: Option<&Path>) -> Result<Self> {
+        let retry = match retry_from {
+            Some(path) => {
+                let mut reader = csv::Reader::from_path(path)
+                    .wrap_err_with(|| format!("failed opening {}", path.display()))?;
+                let items = reader
+                    .deserialize()
+                    .map(|r| r.map(|r: FailureRecord| r.item))
+                    .collect::<Result<_, _>>()
+                    .wrap_err_with(|| format!("failed parsing {}", path.display()))?;
+                Some(items)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            keep_going,
+            retry,
+            failed: Vec::new(),
+        })
+    }
+
+    /// Whether `item` should be processed, false if it is not listed in the `--retry-from` file
+    pub fn selected(&self, item: &str) -> bool {
+        match &self.retry {
+            Some(retry) => retry.contains(item),
+            None => true,
+        }
+    }
+
+    pub fn keep_going(&self) -> bool {
+        self.keep_going
+    }
+
+    /// Records an error and continues, regardless of `--keep-going`
+    pub fn push(&mut self, item: impl Into<String>, e: Report) {
+        self.failed.push((item.into(), e));
+    }
+
+    /// Returns the value of `res`, or `None` after recording the error when keeping going
+    ///
+    /// Without `--keep-going` the error is returned, which aborts the operation.
+    pub fn check<T>(&mut self, item: &str, res: Result<T>) -> Result<Option<T>> {
+        match res {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if self.keep_going => {
+                self.push(item, e);
+                Ok(None)
+            }
+            Err(e) => Err(e.wrap_err(format!("failed for {item}"))),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failed.is_empty()
+    }
+
+    /// Prints a table of everything that failed and writes it to `file`
+    ///
+    /// Returns an error if anything failed, so the program exits with a non-zero status.
+    pub fn finish(self, file: Option<&Path>) -> Result<()> {
+        if self.failed.is_empty() {
+            return Ok(());
+        }
+
+        let width = self
+            .failed
+            .iter()
+            .map(|(item, _)| item.len())
+            .max()
+            .unwrap_or_default();
+
+        eprintln!("Failed for {}:", self.failed.len());
+        for (item, e) in &self.failed {
+            eprintln!("  {item:<width$}  {e:#}");
+        }
+
+        if let Some(path) = file {
+            let mut wtr = csv::Writer::from_path(path)
+                .wrap_err_with(|| format!("could not create {}", path.display()))?;
+            for (item, e) in &self.failed {
+                wtr.serialize(FailureRecord {
+                    item: item.clone(),
+                    error: format!("{e:#}"),
+                })?;
+            }
+            wtr.flush()?;
+            eprintln!(
+                "Failures written to {}, use --retry-from to retry them",
+                path.display()
+            );
+        }
+
+        bail!("{} operations failed", self.failed.len());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::eyre;
+
+    use super::Failures;
+
+    #[test]
+    fn retries_from_failures_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("failures.csv");
+
+        let mut failures = Failures::new(true, None).unwrap();
+        assert_eq!(failures.check("RTS - jdoe", Ok(1)).unwrap(), Some(1));
+        assert_eq!(
+            failures
+                .check::<()>("RTS - asmith", Err(eyre!("not found")))
+                .unwrap(),
+            None
+        );
+        assert!(failures.finish(Some(&path)).is_err());
+
+        let mut retry = Failures::new(false, Some(&path)).unwrap();
+        assert!(retry.selected("RTS - asmith"));
+        assert!(!retry.selected("RTS - jdoe"));
+        assert!(retry.check::<()>("RTS - asmith", Err(eyre!("x"))).is_err());
+        assert!(retry.is_empty());
+    }
+}
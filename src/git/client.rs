@@ -0,0 +1,217 @@
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use gitlab::api::{ApiError, Client, RestClient};
+use gitlab::Gitlab;
+use http::{request::Builder, HeaderMap, Method, Response, StatusCode};
+use url::Url;
+
+/// How often a request is retried after a 429 or 5xx response
+const MAX_RETRIES: u32 = 5;
+
+/// When fewer requests than this are left in the current window, wait for the window to reset
+const LOW_REMAINING: u64 = 5;
+
+/// Gitlab client that is shared between threads, which respects gitlab's rate limits
+///
+/// Every response is checked for the `RateLimit-Remaining` and `RateLimit-Reset` headers. Each
+/// request takes one of the remaining requests before it is sent, once the limit is almost reached
+/// all threads wait until it resets. Requests that are answered with a 429 status, or with a 5xx
+/// status if they are idempotent, are retried after the `Retry-After` delay or with exponential
+/// backoff. Other requests, like creating a project, are never repeated by the client.
+pub struct RateLimitedClient {
+    gitlab: Gitlab,
+    state: Mutex<RateLimitState>,
+}
+
+#[derive(Debug, Default)]
+struct RateLimitState {
+    /// No requests are sent before this instant
+    paused_until: Option<Instant>,
+    /// Requests left in the current window, minus the ones that have been sent since
+    remaining: Option<u64>,
+    /// When the current window ends
+    reset: Option<Instant>,
+    /// Requests which have been sent, but which have not been answered yet
+    in_flight: u64,
+}
+
+impl RateLimitedClient {
+    pub fn new(gitlab: Gitlab) -> Self {
+        Self {
+            gitlab,
+            state: Mutex::new(RateLimitState::default()),
+        }
+    }
+
+    /// Waits until a request may be sent, and takes it from the remaining requests
+    fn reserve(&self) {
+        let mut state = self.state.lock().unwrap();
+
+        loop {
+            let now = Instant::now();
+
+            let paused_until = state.paused_until.filter(|&until| until > now);
+            if let Some(until) = paused_until {
+                drop(state);
+                thread::sleep(until - now);
+                state = self.state.lock().unwrap();
+                continue;
+            }
+
+            if state.reset.is_some_and(|reset| reset <= now) {
+                state.remaining = None;
+                state.reset = None;
+            }
+
+            match (state.remaining, state.reset) {
+                (Some(remaining), Some(reset)) if remaining < LOW_REMAINING => {
+                    state.paused_until = Some(reset);
+                }
+                _ => break,
+            }
+        }
+
+        state.in_flight += 1;
+        if let Some(remaining) = &mut state.remaining {
+            *remaining = remaining.saturating_sub(1);
+        }
+    }
+
+    /// Updates the remaining requests from the headers of a response to a reserved request
+    fn release(&self, headers: Option<&HeaderMap>) {
+        let mut state = self.state.lock().unwrap();
+        state.in_flight -= 1;
+
+        let Some(headers) = headers else {
+            return;
+        };
+        if let Some(remaining) = header::<u64>(headers, "RateLimit-Remaining") {
+            // gitlab has not counted the requests that are still in flight yet
+            state.remaining = Some(remaining.saturating_sub(state.in_flight));
+            state.reset = reset_delay(headers).map(|delay| Instant::now() + delay);
+        }
+    }
+
+    /// Pauses all requests for at least `delay`
+    fn pause(&self, delay: Duration) {
+        let until = Instant::now() + delay;
+
+        let mut state = self.state.lock().unwrap();
+        state.paused_until = Some(state.paused_until.map_or(until, |p| p.max(until)));
+    }
+}
+
+impl RestClient for RateLimitedClient {
+    type Error = <Gitlab as RestClient>::Error;
+
+    fn rest_endpoint(&self, endpoint: &str) -> Result<Url, ApiError<Self::Error>> {
+        self.gitlab.rest_endpoint(endpoint)
+    }
+}
+
+impl Client for RateLimitedClient {
+    fn rest(
+        &self,
+        request: Builder,
+        body: Vec<u8>,
+    ) -> Result<Response<Bytes>, ApiError<Self::Error>> {
+        let mut attempt = 0;
+
+        loop {
+            self.reserve();
+            let rsp = self.gitlab.rest(copy_request(&request), body.clone());
+            self.release(rsp.as_ref().ok().map(|rsp| rsp.headers()));
+
+            let rsp = rsp?;
+            let status = rsp.status();
+
+            // a request which failed with a server error may still have had an effect
+            let retry = status == StatusCode::TOO_MANY_REQUESTS
+                || (status.is_server_error() && is_idempotent(&request));
+            if !retry || attempt >= MAX_RETRIES {
+                return Ok(rsp);
+            }
+
+            self.pause(retry_delay(rsp.headers(), attempt));
+            attempt += 1;
+        }
+    }
+}
+
+/// Request builders can't be cloned, so this creates a new one with the same method, uri and headers
+fn copy_request(request: &Builder) -> Builder {
+    let mut copy = Builder::new();
+
+    if let Some(method) = request.method_ref() {
+        copy = copy.method(method.clone());
+    }
+    if let Some(uri) = request.uri_ref() {
+        copy = copy.uri(uri.clone());
+    }
+    if let Some(&version) = request.version_ref() {
+        copy = copy.version(version);
+    }
+    if let Some(headers) = request.headers_ref() {
+        for (name, value) in headers {
+            copy = copy.header(name, value);
+        }
+    }
+
+    copy
+}
+
+/// Whether sending the request again has the same effect as sending it once
+fn is_idempotent(request: &Builder) -> bool {
+    request
+        .method_ref()
+        .is_some_and(|m| [Method::GET, Method::HEAD, Method::PUT, Method::DELETE].contains(m))
+}
+
+fn header<T: FromStr>(headers: &HeaderMap, name: &str) -> Option<T> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+/// Time until the current rate limit window resets
+fn reset_delay(headers: &HeaderMap) -> Option<Duration> {
+    let reset: u64 = header(headers, "RateLimit-Reset")?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+/// Time to wait before retrying a failed request
+fn retry_delay(headers: &HeaderMap, attempt: u32) -> Duration {
+    header(headers, "Retry-After")
+        .map(Duration::from_secs)
+        .or_else(|| reset_delay(headers).filter(|d| !d.is_zero()))
+        .unwrap_or_else(|| Duration::from_secs(1 << attempt))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::{request::Builder, HeaderMap, HeaderValue, Method};
+
+    use super::{is_idempotent, retry_delay};
+
+    #[test]
+    fn retry_delays() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_delay(&headers, 0), Duration::from_secs(1));
+        assert_eq!(retry_delay(&headers, 3), Duration::from_secs(8));
+
+        headers.insert("Retry-After", HeaderValue::from_static("30"));
+        assert_eq!(retry_delay(&headers, 3), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn retries_only_idempotent_requests() {
+        assert!(is_idempotent(&Builder::new().method(Method::PUT)));
+        assert!(!is_idempotent(&Builder::new().method(Method::POST)));
+    }
+}
@@ -0,0 +1,90 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{Context, Result};
+use gitlab::Gitlab;
+use indicatif::ProgressIterator;
+
+use crate::failures::Failures;
+use crate::git::{local::git, projects::get_projects_by_group};
+use crate::models::ProjectInfo;
+
+enum CloneResult {
+    Cloned,
+    Updated,
+}
+
+/// Clones every project in a group into `out`, projects which were already cloned
+/// before are fetched and pulled instead.
+///
+/// When no branch is given the default branch of each project is used.
+pub fn clone_all(
+    client: &Gitlab,
+    group: u64,
+    out: &Path,
+    branch: Option<&str>,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let projects = get_projects_by_group(client, group)?;
+
+    if !dry_run {
+        fs::create_dir_all(out).wrap_err("could not create output directory")?;
+    }
+
+    let mut cloned = 0;
+    let mut updated = 0;
+
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let branch = branch.or(project.default_branch.as_deref());
+        let dir = out.join(project.remote_name());
+
+        if dry_run {
+            if dir.join(".git").exists() {
+                println!("Dry Run: updating {} in {}", project.name, dir.display());
+            } else {
+                println!("Dry Run: cloning {} into {}", project.name, dir.display());
+            }
+            continue;
+        }
+
+        match clone_or_update(project, out, branch) {
+            Ok(CloneResult::Cloned) => cloned += 1,
+            Ok(CloneResult::Updated) => updated += 1,
+            Err(e) => failures.push(&project.name, e),
+        }
+    }
+
+    println!("Cloned {cloned} and updated {updated} projects.");
+
+    Ok(())
+}
+
+fn clone_or_update(project: &ProjectInfo, out: &Path, branch: Option<&str>) -> Result<CloneResult> {
+    let name = project.remote_name();
+    let dir = out.join(&name);
+
+    if dir.join(".git").exists() {
+        git(&dir, &["fetch", "--all", "--prune"])?;
+
+        if let Some(branch) = branch {
+            git(&dir, &["checkout", branch])?;
+            git(&dir, &["pull", "--ff-only"])?;
+        }
+
+        return Ok(CloneResult::Updated);
+    }
+
+    let mut args = vec!["clone"];
+    if let Some(branch) = branch {
+        args.extend(["--branch", branch]);
+    }
+    args.extend([project.ssh_url_to_repo.as_str(), name.as_str()]);
+
+    git(out, &args)?;
+
+    Ok(CloneResult::Cloned)
+}
@@ -0,0 +1,186 @@
+use std::{fs, path::Path};
+
+use color_eyre::eyre::{Context, Result};
+use gitlab::{
+    api::{paged, raw, Pagination, Query},
+    Gitlab,
+};
+use glob::Pattern;
+use indicatif::ProgressIterator;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::failures::Failures;
+use crate::git::{
+    endpoints::{RawFile, RepositoryTree},
+    projects::get_projects_by_group,
+};
+use crate::models::ProjectInfo;
+
+#[derive(Debug, Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestEntry {
+    project: String,
+    status: &'static str,
+    files: String,
+}
+
+/// Downloads all files matching any of the patterns from every project in a group
+/// into `<out>/<project>/`, without cloning the projects.
+///
+/// A `manifest.csv` is written to `out` listing the collected files per project,
+/// and which projects did not contain any matching file.
+pub fn collect(
+    client: &Gitlab,
+    group: u64,
+    patterns: &[String],
+    ref_: Option<&str>,
+    out: &Path,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let patterns: Vec<Pattern> = patterns
+        .iter()
+        .map(|p| Pattern::new(p).wrap_err_with(|| format!("invalid pattern: {p}")))
+        .collect::<Result<_>>()?;
+
+    let projects = get_projects_by_group(client, group)?;
+
+    if !dry_run {
+        fs::create_dir_all(out).wrap_err("could not create output directory")?;
+    }
+
+    let mut manifest = Vec::with_capacity(projects.len());
+    let mut missing = Vec::new();
+    let mut failed = 0;
+
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let Some(ref_) = ref_.or(project.default_branch.as_deref()) else {
+            missing.push(project.name.as_str());
+            manifest.push(ManifestEntry {
+                project: project.name.clone(),
+                status: "missing",
+                files: String::new(),
+            });
+            continue;
+        };
+
+        match collect_project(client, project, &patterns, ref_, out, dry_run) {
+            Ok(files) if files.is_empty() => {
+                missing.push(project.name.as_str());
+                manifest.push(ManifestEntry {
+                    project: project.name.clone(),
+                    status: "missing",
+                    files: String::new(),
+                });
+            }
+            Ok(files) => manifest.push(ManifestEntry {
+                project: project.name.clone(),
+                status: "found",
+                files: files.join(";"),
+            }),
+            Err(e) => {
+                manifest.push(ManifestEntry {
+                    project: project.name.clone(),
+                    status: "error",
+                    files: String::new(),
+                });
+                failures.push(&project.name, e);
+                failed += 1;
+            }
+        }
+    }
+
+    if dry_run {
+        println!(
+            "Dry Run: not writing {}",
+            out.join("manifest.csv").display()
+        );
+    } else {
+        let mut wtr = csv::Writer::from_path(out.join("manifest.csv"))
+            .wrap_err("could not create manifest file")?;
+        manifest.iter().try_for_each(|e| wtr.serialize(e))?;
+        wtr.flush()?;
+    }
+
+    println!(
+        "Collected files from {} projects.",
+        manifest.len() - missing.len() - failed
+    );
+
+    if !missing.is_empty() {
+        println!(
+            "No matching files in {} projects: {}",
+            missing.len(),
+            missing.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the paths of the matching files in a project
+fn collect_project(
+    client: &Gitlab,
+    project: &ProjectInfo,
+    patterns: &[Pattern],
+    ref_: &str,
+    out: &Path,
+    dry_run: bool,
+) -> Result<Vec<String>> {
+    let endpoint = RepositoryTree {
+        project: project.id,
+        ref_,
+    };
+    let tree: Vec<TreeEntry> = paged(endpoint, Pagination::All)
+        .query(client)
+        .wrap_err("failed listing repository files")?;
+
+    let files = tree
+        .into_iter()
+        .filter(|e| e.kind == "blob")
+        .map(|e| e.path)
+        .filter(|path| {
+            let file_name = path.rsplit('/').next().unwrap_or(path);
+            patterns
+                .iter()
+                .any(|p| p.matches(path) || p.matches(file_name))
+        })
+        .collect_vec();
+
+    let dir = out.join(project.remote_name());
+    for path in &files {
+        if dry_run {
+            println!("Dry Run: downloading {path} from {}", project.name);
+            continue;
+        }
+
+        let endpoint = RawFile {
+            project: project.id,
+            path,
+            ref_,
+        };
+        let contents = raw(endpoint)
+            .query(client)
+            .wrap_err_with(|| format!("failed downloading {path}"))?;
+
+        let target = dir.join(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, contents)
+            .wrap_err_with(|| format!("failed writing {}", target.display()))?;
+    }
+
+    Ok(files)
+}
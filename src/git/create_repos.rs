@@ -1,145 +1,390 @@
+use crate::failures::Failures;
+use crate::git::endpoints::{CreateSubgroup, GroupSubgroups};
 use crate::git::invite;
-use crate::BrightspaceArgs;
-use crate::{
-    brightspace::get_students,
-    models::{Group, ProjectInfo, Student},
-};
+use crate::git::journal::{Journal, Step};
+use crate::git::members::{get_project_invitations, get_project_members};
+use crate::git::parallel;
+use crate::models::{Group, GroupInfo, ProjectInfo, Student};
+use crate::roster::{Roster, RosterStudent};
 use color_eyre::eyre::{Context, Result};
 use gitlab::api::common::VisibilityLevel;
-use gitlab::{
-    api::{
-        common::AccessLevel,
-        projects::{self},
-        Query,
-    },
-    Gitlab,
+use gitlab::api::{
+    common::AccessLevel,
+    paged,
+    projects::{self},
+    Client, Pagination, Query,
 };
 use http::Uri;
-use indicatif::ProgressIterator;
 use itertools::Itertools;
+use std::collections::HashMap;
 
 // Creates Gitlab Repos inviting all group members
-pub fn create_group_repos(
-    client: &Gitlab,
+#[allow(clippy::too_many_arguments)]
+pub fn create_group_repos<C: Client + Sync>(
+    client: &C,
     parent_namespace_id: u64,
     template_url: &str,
     access_level: AccessLevel,
     groups: &[Group],
+    journal: &Journal,
+    jobs: usize,
+    failures: &mut Failures,
     dry_run: bool,
 ) -> Result<()> {
-    let mut n = 0;
-    let mut created = Vec::new();
-    for g in groups.iter().progress() {
-        if dry_run {
-            created.push(g);
-        } else {
+    let existing = existing_projects(crate::projects::get_projects_by_group(
+        client,
+        parent_namespace_id,
+    ))?;
+    for g in groups {
+        adopt_existing_project(journal, &existing, &g.name)?;
+    }
+
+    let (done, todo): (Vec<&Group>, Vec<&Group>) = groups
+        .iter()
+        .partition(|g| journal.progress(&g.name).is_complete());
+
+    let created = parallel::try_for_each(
+        &todo,
+        jobs,
+        failures,
+        |g| g.name.as_str(),
+        |g, _| {
+            if dry_run {
+                return Ok(());
+            }
+
             create_repo_from_template(
                 client,
+                journal,
                 g.members.iter().collect_vec().as_ref(),
                 parent_namespace_id,
                 &g.name,
                 template_url,
                 access_level,
             )
-            .wrap_err(format!("failed creating repo for: {}", g.name))?;
-        }
+            .wrap_err("failed creating repo")
+        },
+    )?;
 
-        n += 1;
-    }
-
-    println!("Created {n} projects successfully.");
+    println!(
+        "Created {} projects successfully, skipped {} finished groups.",
+        created.len(),
+        done.len()
+    );
     if dry_run {
+        let created = created.iter().map(|(g, ())| g).collect_vec();
         println!("Would have created repo for: {created:#?}");
     }
     Ok(())
 }
 
-pub fn create_individual_repos(
-    client: &Gitlab,
+/// Name of the individual repository of a student
+pub fn repo_name(repo_name_prefix: &Option<String>, netid: &str) -> String {
+    if let Some(prefix) = repo_name_prefix {
+        format!("{prefix} - {netid}")
+    } else {
+        netid.to_string()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_individual_repos<C: Client + Sync>(
+    client: &C,
     repo_name_prefix: &Option<String>,
     parent_namespace_id: u64,
     template_url: &str,
     access_level: AccessLevel,
-    brightspace_args: BrightspaceArgs,
-    brightspace_ou: u64,
+    roster: &dyn Roster,
+    by_section: bool,
+    journal: &Journal,
+    jobs: usize,
+    failures: &mut Failures,
     dry_run: bool,
 ) -> Result<()> {
-    let students = get_students(&brightspace_args.base_url, &brightspace_args.cookie, brightspace_ou)
-        .wrap_err("failed getting list of students from brightspace")?;
+    let students = roster
+        .students()
+        .wrap_err("failed getting list of students")?;
 
-    let parent_project_names: Vec<String> =
+    let parent_projects = if by_section {
+        crate::projects::get_projects_by_group_recursive(client, parent_namespace_id)
+    } else {
         crate::projects::get_projects_by_group(client, parent_namespace_id)
-            .wrap_err("failed getting projects under give parent id")?
-            .into_iter()
-            .map(|p| p.name)
-            .collect();
+    };
+    let existing = existing_projects(parent_projects)?;
+
+    let mut subgroups = if by_section {
+        get_subgroups(client, parent_namespace_id)?
+    } else {
+        Vec::new()
+    };
 
-    let mut n = 0;
     let mut skipped = 0;
-    let mut created = Vec::new();
+    // Name, namespace and student of every repository that still has to be created or finished
+    let mut todo = Vec::new();
 
-    for s in students.into_iter().progress() {
-        let name = if let Some(prefix) = &repo_name_prefix {
-            format!("{prefix} - {}", &s.netid)
-        } else {
-            s.netid.clone()
-        };
+    for RosterStudent {
+        student: s,
+        sections,
+        ..
+    } in students
+    {
+        let name = repo_name(repo_name_prefix, &s.netid);
+        if !failures.selected(&name) {
+            continue;
+        }
+        adopt_existing_project(journal, &existing, &name)?;
+        let progress = journal.progress(&name);
 
-        if parent_project_names.iter().any(|pn| pn == &name) {
-            // println!("Skipping {}, already has a repo.", &s.netid);
+        if progress.is_complete() {
             skipped += 1;
             continue;
         }
 
-        if dry_run {
-            created.push(s);
+        // Only needed when the project still has to be created
+        let namespace_id = if by_section && progress.project_id.is_none() {
+            let namespace = section_namespace(
+                client,
+                parent_namespace_id,
+                &mut subgroups,
+                &s,
+                &sections,
+                dry_run,
+            );
+            match failures.check(&name, namespace)? {
+                Some(id) => id,
+                None => continue,
+            }
         } else {
+            parent_namespace_id
+        };
+
+        todo.push((name, namespace_id, s));
+    }
+
+    let created = parallel::try_for_each(
+        &todo,
+        jobs,
+        failures,
+        |(name, _, _)| name.as_str(),
+        |(name, namespace_id, s), _| {
+            if dry_run {
+                return Ok(());
+            }
+
             create_repo_from_template(
                 client,
-                &[&s],
-                parent_namespace_id,
-                &name,
+                journal,
+                &[s],
+                *namespace_id,
+                name,
                 template_url,
                 access_level,
             )
-            .wrap_err("failed creating repo")?;
-        }
+            .wrap_err("failed creating repo")
+        },
+    )?;
 
-        n += 1;
-    }
-
-    println!("Created {n} projects successfully, skipped {skipped} students.");
+    println!(
+        "Created {} projects successfully, skipped {skipped} students.",
+        created.len()
+    );
     if dry_run {
+        let created = created.iter().map(|((_, _, s), ())| s).collect_vec();
         println!("Would have created repo for: {created:?}");
     }
 
     Ok(())
 }
 
-fn create_repo_from_template(
-    client: &Gitlab,
+/// Maps the names of the projects in the parent group to their ids
+fn existing_projects(projects: Result<Vec<ProjectInfo>>) -> Result<HashMap<String, u64>> {
+    Ok(projects
+        .wrap_err("failed getting projects under given parent id")?
+        .into_iter()
+        .map(|p| (p.name, p.id))
+        .collect())
+}
+
+/// Records projects which exist but are not in the journal, created by an earlier run without one
+///
+/// Their members and invitations are still checked, instead of assuming they were finished.
+fn adopt_existing_project(
+    journal: &Journal,
+    existing: &HashMap<String, u64>,
+    name: &str,
+) -> Result<()> {
+    if journal.progress(name).project_id.is_some() {
+        return Ok(());
+    }
+
+    match existing.get(name) {
+        Some(&project_id) => journal.record(name, Step::ProjectCreated { project_id }),
+        None => Ok(()),
+    }
+}
+
+fn get_subgroups<C: Client>(client: &C, group: u64) -> Result<Vec<GroupInfo>> {
+    paged(GroupSubgroups { group }, Pagination::All)
+        .query(client)
+        .wrap_err("failed getting subgroups")
+}
+
+/// Turns a section name into a valid gitlab group path, which none of `subgroups` has yet
+///
+/// Names without any ascii letters or digits get a path based on a hash of the name instead.
+/// As different names can result in the same path, a number is appended to paths already taken.
+fn group_path(name: &str, subgroups: &[GroupInfo]) -> String {
+    let mut path = name
+        .split(|c: char| !c.is_ascii_alphanumeric() && c != '_' && c != '.')
+        .filter(|s| !s.is_empty())
+        .join("-")
+        .to_lowercase();
+
+    if !path.chars().any(|c| c.is_ascii_alphanumeric()) {
+        // FNV-1a, which unlike the std hasher is the same in every build
+        let hash = name.bytes().fold(0x811c9dc5u32, |hash, b| {
+            (hash ^ u32::from(b)).wrapping_mul(0x01000193)
+        });
+        path = format!("section-{hash:08x}");
+    }
+
+    let taken = |p: &str| subgroups.iter().any(|g| g.path.eq_ignore_ascii_case(p));
+    if !taken(&path) {
+        return path;
+    }
+
+    (2..)
+        .map(|i| format!("{path}-{i}"))
+        .find(|p| !taken(p))
+        .expect("some number is not taken")
+}
+
+/// Finds the subgroup for the section of a student, creating it if it doesn't exist yet
+///
+/// Students who are not in any section get their repository in the parent group.
+fn section_namespace<C: Client>(
+    client: &C,
+    parent: u64,
+    subgroups: &mut Vec<GroupInfo>,
+    student: &Student,
+    sections: &[String],
+    dry_run: bool,
+) -> Result<u64> {
+    let Some(section) = sections.first() else {
+        eprintln!(
+            "{} is not in any section, using the parent group",
+            student.netid
+        );
+        return Ok(parent);
+    };
+    if sections.len() > 1 {
+        eprintln!("{} is in multiple sections, using {section}", student.netid);
+    }
+
+    if let Some(group) = subgroups.iter().find(|g| &g.name == section) {
+        return Ok(group.id);
+    }
+
+    let path = group_path(section, subgroups);
+    let group = if dry_run {
+        println!("Dry Run: created subgroup {section} ({path})");
+        GroupInfo {
+            id: parent,
+            name: section.clone(),
+            path,
+        }
+    } else {
+        let endpoint = CreateSubgroup {
+            parent,
+            name: section,
+            path: &path,
+        };
+        endpoint
+            .query(client)
+            .wrap_err_with(|| format!("failed creating subgroup {section}"))?
+    };
+
+    let id = group.id;
+    subgroups.push(group);
+    Ok(id)
+}
+
+/// Creates a repository and adds the students to it, recording every step in the journal
+///
+/// Steps that the journal lists as done are skipped, so a repository left behind half-created by
+/// an earlier run is finished instead of created again.
+fn create_repo_from_template<C: Client>(
+    client: &C,
+    journal: &Journal,
     students: &[&Student],
     parent_namespace_id: u64,
     name: &str,
     template_url: &str,
     access_level: AccessLevel,
 ) -> Result<()> {
-    let endpoint = projects::CreateProject::builder()
-        .visibility(VisibilityLevel::Private)
-        .import_url(template_url)
-        .namespace_id(parent_namespace_id)
-        .name(name)
-        .emails_disabled(true)
-        .build()
-        .wrap_err("createproject builder")?;
+    let progress = journal.progress(name);
+
+    let project_id = match progress.project_id {
+        Some(id) => id,
+        None => {
+            let endpoint = projects::CreateProject::builder()
+                .visibility(VisibilityLevel::Private)
+                .import_url(template_url)
+                .namespace_id(parent_namespace_id)
+                .name(name)
+                .emails_disabled(true)
+                .build()
+                .wrap_err("createproject builder")?;
+
+            let created: Result<ProjectInfo, _> = endpoint.query(client);
+            let project_id = match created {
+                Ok(project) => project.id,
+                // The client does not repeat a failed create, but it may still have succeeded
+                Err(e) => crate::projects::get_projects_by_group(client, parent_namespace_id)
+                    .ok()
+                    .and_then(|projects| projects.into_iter().find(|p| p.name == name))
+                    .map(|p| p.id)
+                    .ok_or(e)
+                    .wrap_err("create project")?,
+            };
+            journal.record(name, Step::ProjectCreated { project_id })?;
+            project_id
+        }
+    };
+
+    if progress.members_added && progress.invitations_sent {
+        return Ok(());
+    }
+
+    let (mut to_add, mut to_invite) = invite::find_users(client, students)?;
+
+    // A project from an earlier run can already have some of the students
+    if progress.project_id.is_some() {
+        let members = get_project_members(client, project_id)?;
+        to_add.retain(|u| !members.iter().any(|m| m.id == u.id));
 
-    let project: ProjectInfo = endpoint.query(client).wrap_err("create project")?;
+        let invitations = get_project_invitations(client, project_id)?;
+        to_invite.retain(|s| {
+            !invitations
+                .iter()
+                .any(|i| i.invite_email.eq_ignore_ascii_case(&s.email))
+        });
+    }
 
-    invite::add_students_to_project(client, project.id, students, access_level)
+    if !progress.members_added {
+        invite::invite_by_userinfo(client, project_id, &to_add, access_level)?;
+        journal.record(name, Step::MembersAdded)?;
+    }
+    if !progress.invitations_sent {
+        invite::invite_by_email(client, project_id, &to_invite, access_level)?;
+        journal.record(name, Step::InvitationsSent)?;
+    }
+
+    Ok(())
 }
 
-fn fork_template(
-    client: &Gitlab,
+fn fork_template<C: Client>(
+    client: &C,
     students: &[&Student],
     group_id: u64,
     name: &str,
@@ -168,7 +413,23 @@ mod tests {
 
     use gitlab::Gitlab;
 
-    use super::fork_template;
+    use super::{fork_template, group_path};
+    use crate::models::GroupInfo;
+
+    #[test]
+    fn section_group_paths() {
+        assert_eq!(group_path("Lab Session A", &[]), "lab-session-a");
+        assert_eq!(group_path("Lab 2 (Tuesday)", &[]), "lab-2-tuesday");
+        assert!(group_path("实验 一", &[]).starts_with("section-"));
+        assert_ne!(group_path("实验 一", &[]), group_path("实验 二", &[]));
+
+        let subgroups = [GroupInfo {
+            id: 1,
+            name: "Lab A".to_string(),
+            path: "lab-a".to_string(),
+        }];
+        assert_eq!(group_path("Lab-A", &subgroups), "lab-a-2");
+    }
 
     #[test]
     #[ignore = "flaky"]
@@ -0,0 +1,422 @@
+use std::{collections::HashMap, fs::File, io, path::Path};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ValueEnum;
+use color_eyre::eyre::{bail, Context, Result};
+use gitlab::{
+    api::{ignore, paged, projects::repository::commits::Commits, ApiError, Pagination, Query},
+    Gitlab,
+};
+use indicatif::ProgressIterator;
+use serde::Serialize;
+
+use crate::failures::Failures;
+use crate::git::{
+    endpoints::{CreateTag, ProjectEvents, ProtectTag, RepositoryCompare, RepositoryTags},
+    projects::get_projects_by_group,
+};
+use crate::models::{Commit, Comparison, ProjectInfo, PushEvent, Tag};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ReportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+struct LateCommit<'a> {
+    project: &'a str,
+    members: String,
+    commit: String,
+    title: String,
+    author_name: String,
+    author_email: String,
+    authored_date: DateTime<Utc>,
+    committed_date: DateTime<Utc>,
+    pushed_by: String,
+    pushed_at: DateTime<Utc>,
+    late_by: String,
+    late_seconds: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct TagReportEntry<'a> {
+    project: &'a str,
+    status: &'static str,
+    commit: Option<String>,
+    committed_date: Option<DateTime<Utc>>,
+    error: Option<String>,
+}
+
+/// Outcome of tagging a single project
+enum Tagged {
+    New(Commit),
+    Existing(Commit),
+    NoCommit,
+}
+
+/// Returns the newest commit on `branch` which was committed before `deadline`
+///
+/// The committer date is set by whoever made the commit, so a commit made before but pushed after
+/// the deadline is still selected. Use [`late_report`] to find those.
+fn last_commit_before(
+    client: &Gitlab,
+    project: u64,
+    branch: &str,
+    deadline: DateTime<Utc>,
+) -> Result<Option<Commit>> {
+    let endpoint = Commits::builder()
+        .project(project)
+        .ref_name(branch)
+        .until(deadline)
+        .build()
+        .wrap_err("commits builder")?;
+
+    let mut commits: Vec<Commit> = paged(endpoint, Pagination::Limit(1))
+        .query(client)
+        .wrap_err("failed listing commits")?;
+
+    Ok(commits.pop())
+}
+
+fn get_tag(client: &Gitlab, project: u64, tag: &str) -> Result<Option<Tag>> {
+    let tags: Vec<Tag> = paged(
+        RepositoryTags {
+            project,
+            prefix: tag,
+        },
+        Pagination::All,
+    )
+    .query(client)
+    .wrap_err("failed listing tags")?;
+
+    Ok(tags.into_iter().find(|t| t.name == tag))
+}
+
+/// Creates a protected tag on the last commit before the deadline in every project of a group
+///
+/// Projects without a commit before the deadline (or without the branch) are reported,
+/// optionally the full report is written as CSV to `report`. Projects which already have the tag
+/// on the same commit are reported as already tagged, on any other commit they fail.
+#[allow(clippy::too_many_arguments)]
+pub fn tag_deadline(
+    client: &Gitlab,
+    group: u64,
+    deadline: DateTime<Utc>,
+    tag: &str,
+    branch: Option<&str>,
+    report: Option<&Path>,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let projects = get_projects_by_group(client, group)?;
+
+    let mut entries = Vec::with_capacity(projects.len());
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let entry = match tag_project(client, project, deadline, tag, branch, dry_run) {
+            Ok(Tagged::New(commit)) => TagReportEntry {
+                project: &project.name,
+                status: "tagged",
+                commit: Some(commit.id),
+                committed_date: Some(commit.committed_date),
+                error: None,
+            },
+            Ok(Tagged::Existing(commit)) => TagReportEntry {
+                project: &project.name,
+                status: "already-tagged",
+                commit: Some(commit.id),
+                committed_date: Some(commit.committed_date),
+                error: None,
+            },
+            Ok(Tagged::NoCommit) => TagReportEntry {
+                project: &project.name,
+                status: "no-commit",
+                commit: None,
+                committed_date: None,
+                error: None,
+            },
+            Err(e) => {
+                let entry = TagReportEntry {
+                    project: &project.name,
+                    status: "failed",
+                    commit: None,
+                    committed_date: None,
+                    error: Some(format!("{e:#}")),
+                };
+                failures.push(&project.name, e);
+                entry
+            }
+        };
+
+        entries.push(entry);
+    }
+
+    let tagged = entries.iter().filter(|e| e.status == "tagged").count();
+    let existing = entries
+        .iter()
+        .filter(|e| e.status == "already-tagged")
+        .count();
+    println!("Tagged {tagged} projects with {tag}, {existing} were already tagged.");
+
+    for entry in entries.iter().filter(|e| e.status == "no-commit") {
+        println!("  {}: no commit before the deadline", entry.project);
+    }
+
+    if let Some(report) = report {
+        let mut wtr = csv::Writer::from_path(report).wrap_err("could not create report file")?;
+        entries.iter().try_for_each(|e| wtr.serialize(e))?;
+        wtr.flush()?;
+    }
+
+    Ok(())
+}
+
+fn tag_project(
+    client: &Gitlab,
+    project: &ProjectInfo,
+    deadline: DateTime<Utc>,
+    tag: &str,
+    branch: Option<&str>,
+    dry_run: bool,
+) -> Result<Tagged> {
+    let Some(branch) = branch.or(project.default_branch.as_deref()) else {
+        return Ok(Tagged::NoCommit);
+    };
+
+    let Some(commit) = last_commit_before(client, project.id, branch, deadline)? else {
+        return Ok(Tagged::NoCommit);
+    };
+
+    if let Some(existing) = get_tag(client, project.id, tag)? {
+        if existing.commit.id == commit.id {
+            return Ok(Tagged::Existing(commit));
+        }
+        bail!(
+            "{tag} already exists on {} instead of {}",
+            existing.commit.short_id,
+            commit.short_id
+        );
+    }
+
+    if dry_run {
+        println!(
+            "Dry Run: tagging {} on {} as {tag}",
+            commit.short_id, project.name
+        );
+        return Ok(Tagged::New(commit));
+    }
+
+    match ignore(ProtectTag {
+        project: project.id,
+        name: tag,
+    })
+    .query(client)
+    {
+        Ok(_) => {}
+        Err(ApiError::GitlabService { status, .. }) if status.as_u16() == 409 => {
+            // already protected
+        }
+        e @ Err(_) => e.wrap_err("failed protecting tag")?,
+    }
+
+    let message = format!("Last commit before {deadline}");
+    ignore(CreateTag {
+        project: project.id,
+        tag_name: tag,
+        ref_: &commit.id,
+        message: Some(&message),
+    })
+    .query(client)
+    .wrap_err("failed creating tag")?;
+
+    Ok(Tagged::New(commit))
+}
+
+/// Lists all commits pushed after the deadline in every project of a group
+///
+/// Pushes are taken from gitlab's events, unlike commit dates these can't be set by the students,
+/// so a commit is late when the push that added it was. `owners` maps project names to the netids
+/// of the students owning the project.
+#[allow(clippy::too_many_arguments)]
+pub fn late_report(
+    client: &Gitlab,
+    group: u64,
+    deadline: DateTime<Utc>,
+    branch: Option<&str>,
+    owners: &HashMap<String, Vec<String>>,
+    format: ReportFormat,
+    output: Option<&Path>,
+    failures: &mut Failures,
+) -> Result<()> {
+    let projects = get_projects_by_group(client, group)?;
+
+    let mut late = Vec::new();
+
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let Some(branch) = branch.or(project.default_branch.as_deref()) else {
+            continue;
+        };
+
+        let commits = match late_commits(client, project.id, branch, deadline) {
+            Ok(commits) => commits,
+            Err(e) => {
+                failures.push(&project.name, e);
+                continue;
+            }
+        };
+
+        let members = owners
+            .get(&project.name)
+            .map(|m| m.join(";"))
+            .unwrap_or_default();
+
+        late.extend(commits.into_iter().map(|(c, p)| {
+            let late_by = p.created_at - deadline;
+            LateCommit {
+                project: &project.name,
+                members: members.clone(),
+                commit: c.id,
+                title: c.title,
+                author_name: c.author_name,
+                author_email: c.author_email,
+                authored_date: c.authored_date,
+                committed_date: c.committed_date,
+                pushed_by: p.author_username,
+                pushed_at: p.created_at,
+                late_by: format_delta(late_by),
+                late_seconds: late_by.num_seconds(),
+            }
+        }));
+    }
+
+    let out: Box<dyn io::Write> = match output {
+        Some(path) => Box::new(File::create(path).wrap_err("could not create output file")?),
+        None => Box::new(io::stdout()),
+    };
+
+    match format {
+        ReportFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(out);
+            late.iter().try_for_each(|c| wtr.serialize(c))?;
+            wtr.flush()?;
+        }
+        ReportFormat::Json => serde_json::to_writer_pretty(out, &late)?,
+    }
+
+    Ok(())
+}
+
+/// Returns the commits pushed to `branch` after `deadline` with the push that added them, oldest first
+fn late_commits(
+    client: &Gitlab,
+    project: u64,
+    branch: &str,
+    deadline: DateTime<Utc>,
+) -> Result<Vec<(Commit, PushEvent)>> {
+    let mut commits = Vec::new();
+
+    for push in pushes_after(client, project, branch, deadline)? {
+        for commit in pushed_commits(client, project, &push)? {
+            commits.push((commit, push.clone()));
+        }
+    }
+
+    Ok(commits)
+}
+
+/// Returns the commits added to the branch by a push, oldest first
+fn pushed_commits(client: &Gitlab, project: u64, push: &PushEvent) -> Result<Vec<Commit>> {
+    // the branch was deleted
+    let Some(to) = push.push_data.commit_to.as_deref() else {
+        return Ok(Vec::new());
+    };
+
+    if let Some(from) = push.push_data.commit_from.as_deref() {
+        let comparison: Comparison = RepositoryCompare { project, from, to }
+            .query(client)
+            .wrap_err("failed comparing pushed commits")?;
+        return Ok(comparison.commits);
+    }
+
+    // a new branch, of which gitlab counts the commits that are not on any other branch
+    let endpoint = Commits::builder()
+        .project(project)
+        .ref_name(to)
+        .build()
+        .wrap_err("commits builder")?;
+
+    let mut commits: Vec<Commit> = paged(
+        endpoint,
+        Pagination::Limit(push.push_data.commit_count as usize),
+    )
+    .query(client)
+    .wrap_err("failed listing pushed commits")?;
+    commits.reverse();
+
+    Ok(commits)
+}
+
+/// Returns all pushes to `branch` after `deadline`, oldest first
+fn pushes_after(
+    client: &Gitlab,
+    project: u64,
+    branch: &str,
+    deadline: DateTime<Utc>,
+) -> Result<Vec<PushEvent>> {
+    // `after` only takes a date and excludes it
+    let endpoint = ProjectEvents {
+        project,
+        action: "pushed",
+        after: (deadline - TimeDelta::days(1)).date_naive(),
+    };
+
+    let mut pushes: Vec<PushEvent> = paged(endpoint, Pagination::All)
+        .query(client)
+        .wrap_err("failed listing push events")?;
+
+    pushes.retain(|p| {
+        p.created_at > deadline
+            && p.push_data.ref_type == "branch"
+            && p.push_data.ref_.as_deref() == Some(branch)
+    });
+    pushes.sort_by_key(|p| p.created_at);
+
+    Ok(pushes)
+}
+
+/// Formats a duration like `1d 2h 3m`
+fn format_delta(delta: TimeDelta) -> String {
+    let days = delta.num_days();
+    let hours = delta.num_hours() % 24;
+    let minutes = delta.num_minutes() % 60;
+
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+
+    use super::format_delta;
+
+    #[test]
+    fn formats_delta() {
+        assert_eq!(format_delta(TimeDelta::minutes(5)), "5m");
+        assert_eq!(format_delta(TimeDelta::minutes(125)), "2h 5m");
+        assert_eq!(format_delta(TimeDelta::minutes(60 * 24 + 1)), "1d 0h 1m");
+    }
+}
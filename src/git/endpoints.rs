@@ -0,0 +1,373 @@
+//! Gitlab API endpoints which are missing from the `gitlab` crate
+
+use std::borrow::Cow;
+
+use chrono::NaiveDate;
+use gitlab::api::{BodyError, Endpoint, FormParams, Pageable, QueryParams};
+use http::Method;
+
+/// Percent-encodes a path segment for use inside of an url
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Recursively lists all files and directories in a repository
+///
+/// See <https://docs.gitlab.com/ee/api/repositories.html#list-repository-tree>
+pub struct RepositoryTree<'a> {
+    pub project: u64,
+    pub ref_: &'a str,
+}
+
+impl Endpoint for RepositoryTree<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/tree", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams {
+        let mut params = QueryParams::default();
+        params.push("ref", self.ref_).push("recursive", true);
+        params
+    }
+}
+
+impl Pageable for RepositoryTree<'_> {}
+
+/// Retrieves the raw contents of a file in a repository
+///
+/// See <https://docs.gitlab.com/ee/api/repository_files.html#get-raw-file-from-repository>
+pub struct RawFile<'a> {
+    pub project: u64,
+    pub path: &'a str,
+    pub ref_: &'a str,
+}
+
+impl Endpoint for RawFile<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/repository/files/{}/raw",
+            self.project,
+            encode_path(self.path)
+        )
+        .into()
+    }
+
+    fn parameters(&self) -> QueryParams {
+        let mut params = QueryParams::default();
+        params.push("ref", self.ref_);
+        params
+    }
+}
+
+/// Changes the access level of a member of a project
+///
+/// See <https://docs.gitlab.com/ee/api/members.html#edit-a-member-of-a-group-or-project>
+pub struct EditProjectMember {
+    pub project: u64,
+    pub user: u64,
+    pub access_level: u64,
+}
+
+impl Endpoint for EditProjectMember {
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/members/{}", self.project, self.user).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("access_level", self.access_level);
+        params.into_body()
+    }
+}
+
+/// Lists the pending invitations of a project
+///
+/// See <https://docs.gitlab.com/ee/api/invitations.html#list-all-invitations-pending-for-a-group-or-project>
+pub struct ProjectInvitations {
+    pub project: u64,
+}
+
+impl Endpoint for ProjectInvitations {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/invitations", self.project).into()
+    }
+}
+
+impl Pageable for ProjectInvitations {}
+
+/// Changes the access level of a pending invitation of a project
+///
+/// See <https://docs.gitlab.com/ee/api/invitations.html#update-an-invitation-to-a-group-or-project>
+pub struct EditProjectInvitation<'a> {
+    pub project: u64,
+    pub email: &'a str,
+    pub access_level: u64,
+}
+
+impl Endpoint for EditProjectInvitation<'_> {
+    fn method(&self) -> Method {
+        Method::PUT
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/invitations/{}",
+            self.project,
+            encode_path(self.email)
+        )
+        .into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params.push("access_level", self.access_level);
+        params.into_body()
+    }
+}
+
+/// Deletes a pending invitation of a project
+///
+/// See <https://docs.gitlab.com/ee/api/invitations.html#delete-an-invitation-to-a-group-or-project>
+pub struct DeleteProjectInvitation<'a> {
+    pub project: u64,
+    pub email: &'a str,
+}
+
+impl Endpoint for DeleteProjectInvitation<'_> {
+    fn method(&self) -> Method {
+        Method::DELETE
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!(
+            "projects/{}/invitations/{}",
+            self.project,
+            encode_path(self.email)
+        )
+        .into()
+    }
+}
+
+/// Lists the events of a project with a certain action
+///
+/// See <https://docs.gitlab.com/ee/api/events.html#list-a-projects-visible-events>
+pub struct ProjectEvents<'a> {
+    pub project: u64,
+    pub action: &'a str,
+    /// Only events created after this date, which is exclusive and in UTC
+    pub after: NaiveDate,
+}
+
+impl Endpoint for ProjectEvents<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/events", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams {
+        let mut params = QueryParams::default();
+        params
+            .push("action", self.action)
+            .push("after", self.after.format("%Y-%m-%d").to_string());
+        params
+    }
+}
+
+impl Pageable for ProjectEvents<'_> {}
+
+/// Lists the commits reachable from `to` but not from `from`
+///
+/// See <https://docs.gitlab.com/ee/api/repositories.html#compare-branches-tags-or-commits>
+pub struct RepositoryCompare<'a> {
+    pub project: u64,
+    pub from: &'a str,
+    pub to: &'a str,
+}
+
+impl Endpoint for RepositoryCompare<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/compare", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams {
+        let mut params = QueryParams::default();
+        params.push("from", self.from).push("to", self.to);
+        params
+    }
+}
+
+/// Archives a project, making it read-only
+///
+/// See <https://docs.gitlab.com/ee/api/projects.html#archive-a-project>
+pub struct ArchiveProject {
+    pub project: u64,
+}
+
+impl Endpoint for ArchiveProject {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/archive", self.project).into()
+    }
+}
+
+/// Creates a tag in a repository
+///
+/// See <https://docs.gitlab.com/ee/api/tags.html#create-a-new-tag>
+pub struct CreateTag<'a> {
+    pub project: u64,
+    pub tag_name: &'a str,
+    pub ref_: &'a str,
+    pub message: Option<&'a str>,
+}
+
+impl Endpoint for CreateTag<'_> {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/tags", self.project).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("tag_name", self.tag_name)
+            .push("ref", self.ref_)
+            .push_opt("message", self.message);
+        params.into_body()
+    }
+}
+
+/// Lists the tags of a repository whose names start with `prefix`
+///
+/// See <https://docs.gitlab.com/ee/api/tags.html#list-project-repository-tags>
+pub struct RepositoryTags<'a> {
+    pub project: u64,
+    pub prefix: &'a str,
+}
+
+impl Endpoint for RepositoryTags<'_> {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/repository/tags", self.project).into()
+    }
+
+    fn parameters(&self) -> QueryParams {
+        let mut params = QueryParams::default();
+        params.push("search", format!("^{}", self.prefix));
+        params
+    }
+}
+
+impl Pageable for RepositoryTags<'_> {}
+
+/// Protects tags matching a name (or wildcard), only allowing maintainers to create them
+///
+/// See <https://docs.gitlab.com/ee/api/protected_tags.html#protect-repository-tags>
+pub struct ProtectTag<'a> {
+    pub project: u64,
+    pub name: &'a str,
+}
+
+impl Endpoint for ProtectTag<'_> {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("projects/{}/protected_tags", self.project).into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("name", self.name)
+            .push("create_access_level", 40u64);
+        params.into_body()
+    }
+}
+
+/// Lists the direct subgroups of a group
+///
+/// See <https://docs.gitlab.com/ee/api/groups.html#list-a-groups-subgroups>
+pub struct GroupSubgroups {
+    pub group: u64,
+}
+
+impl Endpoint for GroupSubgroups {
+    fn method(&self) -> Method {
+        Method::GET
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        format!("groups/{}/subgroups", self.group).into()
+    }
+}
+
+impl Pageable for GroupSubgroups {}
+
+/// Creates a private subgroup inside of a group
+///
+/// See <https://docs.gitlab.com/ee/api/groups.html#new-subgroup>
+pub struct CreateSubgroup<'a> {
+    pub parent: u64,
+    pub name: &'a str,
+    pub path: &'a str,
+}
+
+impl Endpoint for CreateSubgroup<'_> {
+    fn method(&self) -> Method {
+        Method::POST
+    }
+
+    fn endpoint(&self) -> Cow<'static, str> {
+        "groups".into()
+    }
+
+    fn body(&self) -> Result<Option<(&'static str, Vec<u8>)>, BodyError> {
+        let mut params = FormParams::default();
+        params
+            .push("name", self.name)
+            .push("path", self.path)
+            .push("parent_id", self.parent)
+            .push("visibility", "private");
+        params.into_body()
+    }
+}
@@ -0,0 +1,282 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use color_eyre::eyre::{bail, eyre, Context, Result};
+use gitlab::{api::common::AccessLevel, Gitlab};
+use indicatif::ProgressIterator;
+use serde::{Deserialize, Serialize};
+
+use crate::failures::Failures;
+use crate::git::{
+    members::{edit_invitation, edit_member, get_project_invitations, get_project_members},
+    projects::get_projects_by_group,
+};
+use crate::models::ProjectInfo;
+
+/// Access levels of members before a freeze, used to restore them afterwards
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FreezeState {
+    group: u64,
+    /// project id -> previous access levels
+    projects: BTreeMap<u64, FrozenProject>,
+}
+
+/// Access levels of the members and pending invitations of a project before a freeze
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FrozenProject {
+    name: String,
+    /// user id -> previous access level
+    members: BTreeMap<u64, u64>,
+    /// invited email -> previous access level
+    invitations: BTreeMap<String, u64>,
+}
+
+impl FrozenProject {
+    fn is_empty(&self) -> bool {
+        self.members.is_empty() && self.invitations.is_empty()
+    }
+}
+
+impl FreezeState {
+    fn load(path: &Path, group: u64) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self {
+                group,
+                ..Default::default()
+            });
+        }
+
+        let state: Self = serde_json::from_str(&fs::read_to_string(path)?)
+            .wrap_err_with(|| format!("failed parsing {}", path.display()))?;
+
+        if state.group != group {
+            bail!(
+                "{} belongs to group {}, not {group}",
+                path.display(),
+                state.group
+            );
+        }
+
+        Ok(state)
+    }
+
+    fn project(&mut self, project: &ProjectInfo) -> &mut FrozenProject {
+        self.projects
+            .entry(project.id)
+            .or_insert_with(|| FrozenProject {
+                name: project.name.clone(),
+                ..Default::default()
+            })
+    }
+
+    /// Writes the state to a temporary file first, so `path` is never left half written
+    fn save(&self, path: &Path) -> Result<()> {
+        if self.projects.values().all(FrozenProject::is_empty) {
+            if path.exists() {
+                fs::remove_file(path)?;
+            }
+            return Ok(());
+        }
+
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_string_pretty(self)?)
+            .wrap_err_with(|| format!("failed writing {}", tmp.display()))?;
+        fs::rename(&tmp, path).wrap_err_with(|| format!("failed writing {}", path.display()))
+    }
+}
+
+/// Lowers the access level of every student in every project of a group to `access_level`
+///
+/// Pending invitations are lowered as well, members with at least maintainer access and the token
+/// owner are left alone. The previous access levels are stored in `state_path` so that
+/// [`unfreeze`] can restore them. Every level is stored before it is changed, so an interrupted
+/// freeze can still be undone, and freezing again before unfreezing keeps the recorded levels.
+pub fn freeze(
+    client: &Gitlab,
+    group: u64,
+    token_user: &str,
+    access_level: AccessLevel,
+    state_path: &Path,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let mut state = FreezeState::load(state_path, group)?;
+    let projects = get_projects_by_group(client, group)?;
+
+    let mut n = 0;
+
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let (members, invitations) = match get_project_members(client, project.id)
+            .and_then(|m| Ok((m, get_project_invitations(client, project.id)?)))
+        {
+            Ok(res) => res,
+            Err(e) => {
+                failures.push(&project.name, e);
+                continue;
+            }
+        };
+
+        for member in members {
+            if member.access_level >= AccessLevel::Maintainer.as_u64()
+                || member.username == token_user
+                || member.access_level <= access_level.as_u64()
+            {
+                continue;
+            }
+
+            if dry_run {
+                println!(
+                    "Dry Run: lowering {} on {} from {} to {}",
+                    member.username,
+                    project.name,
+                    member.access_level,
+                    access_level.as_u64()
+                );
+                continue;
+            }
+
+            state
+                .project(project)
+                .members
+                .entry(member.id)
+                .or_insert(member.access_level);
+            state.save(state_path)?;
+
+            match edit_member(client, project.id, member.id, access_level.as_u64()) {
+                Ok(()) => n += 1,
+                Err(e) => failures.push(&project.name, e),
+            }
+        }
+
+        for invitation in invitations {
+            if invitation.access_level >= AccessLevel::Maintainer.as_u64()
+                || invitation.access_level <= access_level.as_u64()
+            {
+                continue;
+            }
+
+            if dry_run {
+                println!(
+                    "Dry Run: lowering the invitation of {} to {} from {} to {}",
+                    invitation.invite_email,
+                    project.name,
+                    invitation.access_level,
+                    access_level.as_u64()
+                );
+                continue;
+            }
+
+            state
+                .project(project)
+                .invitations
+                .entry(invitation.invite_email.clone())
+                .or_insert(invitation.access_level);
+            state.save(state_path)?;
+
+            match edit_invitation(
+                client,
+                project.id,
+                &invitation.invite_email,
+                access_level.as_u64(),
+            ) {
+                Ok(()) => n += 1,
+                Err(e) => failures.push(&project.name, e),
+            }
+        }
+    }
+
+    if !dry_run {
+        println!(
+            "Lowered the access of {n} members and invitations, previous access levels are stored in {}",
+            state_path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Restores the access levels recorded by [`freeze`]
+pub fn unfreeze(
+    client: &Gitlab,
+    group: u64,
+    state_path: &Path,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    if !state_path.exists() {
+        return Err(eyre!(
+            "{} does not exist, is group {group} frozen?",
+            state_path.display()
+        ));
+    }
+
+    let mut state = FreezeState::load(state_path, group)?;
+
+    let mut n = 0;
+
+    for (&project, frozen) in state.projects.iter_mut().progress() {
+        if !failures.selected(&frozen.name) {
+            continue;
+        }
+
+        frozen.members.retain(|&user, &mut access_level| {
+            if dry_run {
+                println!(
+                    "Dry Run: restoring user {user} on {} to {access_level}",
+                    frozen.name
+                );
+                return true;
+            }
+
+            match edit_member(client, project, user, access_level) {
+                Ok(()) => {
+                    n += 1;
+                    false
+                }
+                Err(e) => {
+                    failures.push(&frozen.name, e.wrap_err(format!("user {user}")));
+                    true
+                }
+            }
+        });
+
+        frozen.invitations.retain(|email, &mut access_level| {
+            if dry_run {
+                println!(
+                    "Dry Run: restoring the invitation of {email} to {} to {access_level}",
+                    frozen.name
+                );
+                return true;
+            }
+
+            match edit_invitation(client, project, email, access_level) {
+                Ok(()) => {
+                    n += 1;
+                    false
+                }
+                Err(e) => {
+                    failures.push(&frozen.name, e.wrap_err(format!("invitation {email}")));
+                    true
+                }
+            }
+        });
+    }
+
+    if !dry_run {
+        state.projects.retain(|_, frozen| !frozen.is_empty());
+        state.save(state_path)?;
+        println!("Restored the access of {n} members and invitations");
+    }
+
+    if !failures.is_empty() {
+        eprintln!(
+            "The access levels that could not be restored are kept in {}",
+            state_path.display()
+        );
+    }
+
+    Ok(())
+}
@@ -4,7 +4,6 @@ use color_eyre::{Result, Section};
 use gitlab::api::common::AccessLevel;
 use gitlab::api::projects::members;
 use gitlab::api::{ignore, users, Client, FormParams, Query, RestClient};
-use gitlab::Gitlab;
 use http::header;
 use http::request::Builder as RequestBuilder;
 use itertools::Itertools;
@@ -15,16 +14,29 @@ use serde::Deserialize;
 /// This will either
 /// * Find if the student already has an existing gitlab account and invite them based on that
 /// * If not, invite the student via e-mail
-pub fn add_students_to_project(
-    client: &Gitlab,
+pub fn add_students_to_project<C: Client>(
+    client: &C,
     project: u64,
     students: &[&Student],
     access_level: AccessLevel,
 ) -> Result<()> {
-    // These people will be invited by email
-    let mut to_invite = vec![];
+    let (to_add, to_invite) = find_users(client, students)?;
+
+    invite_by_email(client, project, &to_invite, access_level)?;
+    invite_by_userinfo(client, project, &to_add, access_level)?;
+
+    Ok(())
+}
+
+/// Splits students into those who already have a gitlab account and those who have to be invited by e-mail
+pub fn find_users<'a, C: Client>(
+    client: &C,
+    students: &[&'a Student],
+) -> Result<(Vec<UserInfo>, Vec<&'a Student>)> {
     // These people will be added based on git ID
     let mut to_add = vec![];
+    // These people will be invited by email
+    let mut to_invite = vec![];
 
     for &student in students {
         if let Some(id) = query_user(client, student)? {
@@ -34,20 +46,18 @@ pub fn add_students_to_project(
         }
     }
 
-    invite_by_email(client, project, &to_invite, access_level)?;
-    invite_by_userinfo(client, project, &to_add, access_level)?;
-
-    Ok(())
+    Ok((to_add, to_invite))
 }
 
 #[derive(Debug, Deserialize)]
 pub struct UserInfo {
-    id: u64,
+    pub id: u64,
+    pub username: String,
 }
 
 /// Queries Gitlab to see if a certain student already has a gitlab account, if so, return `UserInfo`.
-fn query_user(client: &Gitlab, student: &Student) -> Result<Option<UserInfo>> {
-    fn query_user_by_username(client: &Gitlab, student: &str) -> Result<Option<UserInfo>> {
+fn query_user<C: Client>(client: &C, student: &Student) -> Result<Option<UserInfo>> {
+    fn query_user_by_username<C: Client>(client: &C, student: &str) -> Result<Option<UserInfo>> {
         let endpoint = users::Users::builder()
             .username(student)
             .build()
@@ -58,7 +68,7 @@ fn query_user(client: &Gitlab, student: &Student) -> Result<Option<UserInfo>> {
         Ok((!users.is_empty()).then(|| users.swap_remove(0)))
     }
 
-    fn query_user_by_email(client: &Gitlab, student: &Student) -> Result<Option<UserInfo>> {
+    fn query_user_by_email<C: Client>(client: &C, student: &Student) -> Result<Option<UserInfo>> {
         let endpoint = users::Users::builder()
             .search(&student.email)
             .build()
@@ -75,8 +85,8 @@ fn query_user(client: &Gitlab, student: &Student) -> Result<Option<UserInfo>> {
 }
 
 /// Invites students to an existing project if userid is known
-fn invite_by_userinfo(
-    client: &Gitlab,
+pub fn invite_by_userinfo<C: Client>(
+    client: &C,
     id: u64,
     students: &[UserInfo],
     access_level: AccessLevel,
@@ -101,8 +111,8 @@ fn invite_by_userinfo(
 
 /// invites students to a git project by project id and student e-mail
 /// See <https://docs.gitlab.com/ee/api/invitations.html>
-pub fn invite_by_email(
-    client: &Gitlab,
+pub fn invite_by_email<C: Client>(
+    client: &C,
     id: u64,
     students: &[&Student],
     access_level: AccessLevel,
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use color_eyre::eyre::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// A step in the creation of a repository
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    ProjectCreated { project_id: u64 },
+    MembersAdded,
+    InvitationsSent,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    repo: String,
+    #[serde(flatten)]
+    step: Step,
+}
+
+/// The steps that have been completed for a repository
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Progress {
+    pub project_id: Option<u64>,
+    pub members_added: bool,
+    pub invitations_sent: bool,
+}
+
+impl Progress {
+    pub fn is_complete(&self) -> bool {
+        self.project_id.is_some() && self.members_added && self.invitations_sent
+    }
+
+    fn apply(&mut self, step: Step) {
+        match step {
+            Step::ProjectCreated { project_id } => self.project_id = Some(project_id),
+            Step::MembersAdded => self.members_added = true,
+            Step::InvitationsSent => self.invitations_sent = true,
+        }
+    }
+}
+
+/// Append-only record of the steps taken while creating repositories, one json object per line
+///
+/// Every step is written as soon as it succeeded, so an interrupted run can be resumed by opening
+/// the same journal again and finishing the steps that are missing.
+/// It can be shared between threads that each create their own repositories.
+#[derive(Debug)]
+pub struct Journal {
+    path: PathBuf,
+    progress: Mutex<HashMap<String, Progress>>,
+    file: Option<Mutex<File>>,
+}
+
+impl Journal {
+    /// Reads the journal at `path`, creating it if it doesn't exist yet
+    ///
+    /// With `dry_run` the journal is only read and nothing is ever written to it.
+    pub fn open(path: &Path, dry_run: bool) -> Result<Self> {
+        let mut progress: HashMap<String, Progress> = HashMap::new();
+
+        if path.exists() {
+            let contents = fs::read_to_string(path)
+                .wrap_err_with(|| format!("failed reading journal {}", path.display()))?;
+
+            for (i, line) in contents.lines().enumerate() {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry: Entry = serde_json::from_str(line).wrap_err_with(|| {
+                    format!("failed parsing line {} of {}", i + 1, path.display())
+                })?;
+                progress.entry(entry.repo).or_default().apply(entry.step);
+            }
+        }
+
+        let file = if dry_run {
+            None
+        } else {
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .wrap_err_with(|| format!("failed opening journal {}", path.display()))?;
+            Some(Mutex::new(file))
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            progress: Mutex::new(progress),
+            file,
+        })
+    }
+
+    /// The steps completed for `repo` in this or earlier runs
+    pub fn progress(&self, repo: &str) -> Progress {
+        let progress = self.progress.lock().unwrap();
+        progress.get(repo).copied().unwrap_or_default()
+    }
+
+    /// Records that `step` was completed for `repo`
+    pub fn record(&self, repo: &str, step: Step) -> Result<()> {
+        if let Some(file) = &self.file {
+            let mut file = file.lock().unwrap();
+            let entry = Entry {
+                repo: repo.to_string(),
+                step,
+            };
+            let line = serde_json::to_string(&entry)?;
+            writeln!(file, "{line}")
+                .and_then(|_| file.flush())
+                .wrap_err_with(|| format!("failed writing journal {}", self.path.display()))?;
+        }
+
+        self.progress
+            .lock()
+            .unwrap()
+            .entry(repo.to_string())
+            .or_default()
+            .apply(step);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Journal, Progress, Step};
+
+    #[test]
+    fn resumes_from_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+
+        let journal = Journal::open(&path, false).unwrap();
+        journal
+            .record("RTS - jdoe", Step::ProjectCreated { project_id: 42 })
+            .unwrap();
+        journal.record("RTS - jdoe", Step::MembersAdded).unwrap();
+        drop(journal);
+
+        let journal = Journal::open(&path, false).unwrap();
+        assert_eq!(
+            journal.progress("RTS - jdoe"),
+            Progress {
+                project_id: Some(42),
+                members_added: true,
+                invitations_sent: false,
+            }
+        );
+        assert!(!journal.progress("RTS - jdoe").is_complete());
+        assert_eq!(journal.progress("RTS - asmith"), Progress::default());
+    }
+}
@@ -0,0 +1,38 @@
+use std::{path::Path, process::Command};
+
+use color_eyre::eyre::{eyre, Context, Result};
+
+/// Runs `git` with the given arguments inside of `dir` and returns its stdout
+pub fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .wrap_err("failed to run git")?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(eyre!(
+            "git {} failed ({}): {}",
+            args.first().unwrap_or(&""),
+            output.status,
+            stderr.trim()
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Runs `git` with the given arguments inside of `dir` and returns whether it exited successfully
+///
+/// Useful for commands that communicate their answer through the exit code, like `merge-base --is-ancestor`.
+pub fn git_succeeds(dir: &Path, args: &[&str]) -> Result<bool> {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .output()
+        .wrap_err("failed to run git")?
+        .status;
+
+    Ok(status.success())
+}
@@ -0,0 +1,83 @@
+use color_eyre::eyre::{Context, Result};
+use gitlab::{
+    api::{
+        ignore, paged,
+        projects::members::{ProjectMembers, RemoveProjectMember},
+        Client, Pagination, Query,
+    },
+    Gitlab,
+};
+
+use crate::git::endpoints::{
+    DeleteProjectInvitation, EditProjectInvitation, EditProjectMember, ProjectInvitations,
+};
+use crate::models::{ProjectInvitation, ProjectMember};
+
+/// Lists the direct members of a project, members inherited from parent groups are not included
+pub fn get_project_members<C: Client>(client: &C, project: u64) -> Result<Vec<ProjectMember>> {
+    let endpoint = ProjectMembers::builder()
+        .project(project)
+        .build()
+        .wrap_err("project members builder")?;
+
+    paged(endpoint, Pagination::All)
+        .query(client)
+        .wrap_err("failed listing project members")
+}
+
+/// Lists the invitations of a project which have not been accepted yet
+pub fn get_project_invitations<C: Client>(
+    client: &C,
+    project: u64,
+) -> Result<Vec<ProjectInvitation>> {
+    paged(ProjectInvitations { project }, Pagination::All)
+        .query(client)
+        .wrap_err("failed listing project invitations")
+}
+
+pub fn remove_member(client: &Gitlab, project: u64, user: u64) -> Result<()> {
+    let endpoint = RemoveProjectMember::builder()
+        .project(project)
+        .user(user)
+        .build()
+        .wrap_err("remove project member builder")?;
+
+    ignore(endpoint)
+        .query(client)
+        .wrap_err("failed removing project member")
+}
+
+pub fn edit_member(client: &Gitlab, project: u64, user: u64, access_level: u64) -> Result<()> {
+    let endpoint = EditProjectMember {
+        project,
+        user,
+        access_level,
+    };
+
+    ignore(endpoint)
+        .query(client)
+        .wrap_err("failed editing project member")
+}
+
+pub fn edit_invitation(
+    client: &Gitlab,
+    project: u64,
+    email: &str,
+    access_level: u64,
+) -> Result<()> {
+    let endpoint = EditProjectInvitation {
+        project,
+        email,
+        access_level,
+    };
+
+    ignore(endpoint)
+        .query(client)
+        .wrap_err("failed editing project invitation")
+}
+
+pub fn delete_invitation(client: &Gitlab, project: u64, email: &str) -> Result<()> {
+    ignore(DeleteProjectInvitation { project, email })
+        .query(client)
+        .wrap_err("failed deleting project invitation")
+}
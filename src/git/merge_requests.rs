@@ -0,0 +1,123 @@
+use std::path::Path;
+
+use color_eyre::eyre::{Context, Result};
+use gitlab::{
+    api::{
+        projects::merge_requests::{CreateMergeRequest, MergeRequestState, MergeRequests},
+        Query,
+    },
+    Gitlab,
+};
+use indicatif::ProgressIterator;
+use serde::Deserialize;
+
+use crate::failures::Failures;
+use crate::git::{local::git, projects::get_projects_by_group};
+use crate::models::ProjectInfo;
+
+#[derive(Debug, Deserialize)]
+struct MergeRequestInfo {
+    web_url: String,
+}
+
+/// Settings of the merge requests created by [`create_fix_mrs`]
+pub struct FixMergeRequest<'a> {
+    pub source_branch: &'a str,
+    pub target_branch: &'a str,
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    pub remove_source_branch: bool,
+    pub assignee: Option<u64>,
+}
+
+/// Pushes a branch from a local template repository to every project in a group,
+/// and opens a merge request for it.
+///
+/// Projects which already have an open merge request from the source branch are skipped.
+pub fn create_fix_mrs(
+    client: &Gitlab,
+    template: &Path,
+    group: u64,
+    mr: &FixMergeRequest,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let projects = get_projects_by_group(client, group)?;
+
+    let mut created = 0;
+    let mut skipped = 0;
+
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        match create_fix_mr(client, template, project, mr, dry_run) {
+            Ok(true) => created += 1,
+            Ok(false) => skipped += 1,
+            Err(e) => failures.push(&project.name, e),
+        }
+    }
+
+    println!(
+        "Created {created} merge requests, skipped {skipped} projects with an open merge request."
+    );
+
+    Ok(())
+}
+
+/// Returns whether a merge request was (or would have been) created
+fn create_fix_mr(
+    client: &Gitlab,
+    template: &Path,
+    project: &ProjectInfo,
+    mr: &FixMergeRequest,
+    dry_run: bool,
+) -> Result<bool> {
+    let endpoint = MergeRequests::builder()
+        .project(project.id)
+        .state(MergeRequestState::Opened)
+        .source_branch(mr.source_branch)
+        .build()?;
+
+    let open: Vec<MergeRequestInfo> = endpoint.query(client)?;
+    if !open.is_empty() {
+        return Ok(false);
+    }
+
+    if dry_run {
+        println!(
+            "Dry Run: pushing {} and opening a merge request into {} on {}",
+            mr.source_branch, mr.target_branch, project.name
+        );
+        return Ok(true);
+    }
+
+    let refspec = format!("{0}:refs/heads/{0}", mr.source_branch);
+    git(
+        template,
+        &["push", "--quiet", &project.ssh_url_to_repo, &refspec],
+    )?;
+
+    let mut builder = CreateMergeRequest::builder();
+    builder
+        .project(project.id)
+        .source_branch(mr.source_branch)
+        .target_branch(mr.target_branch)
+        .title(mr.title)
+        .remove_source_branch(mr.remove_source_branch);
+
+    if let Some(description) = mr.description {
+        builder.description(description);
+    }
+    if let Some(assignee) = mr.assignee {
+        builder.assignee(assignee);
+    }
+
+    let endpoint = builder.build().wrap_err("create merge request builder")?;
+    let _: MergeRequestInfo = endpoint
+        .query(client)
+        .wrap_err("failed creating merge request")?;
+
+    Ok(true)
+}
@@ -1,3 +1,17 @@
+pub mod client;
+pub mod clone;
+pub mod collect;
 pub mod create_repos;
+pub mod deadline;
+pub mod endpoints;
+pub mod freeze;
 pub mod invite;
+pub mod journal;
+pub mod local;
+pub mod members;
+pub mod merge_requests;
+pub mod parallel;
 pub mod projects;
+pub mod push_template;
+pub mod sync_groups;
+pub mod sync_roster;
@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use color_eyre::eyre::Result;
+use indicatif::ProgressBar;
+
+use crate::failures::Failures;
+
+/// Runs `f` for every item on up to `jobs` threads while showing a progress bar
+///
+/// Items that are not selected by `--retry-from` are skipped, errors are recorded in `failures`
+/// under the name of the item. Without `--keep-going` no new items are started after one failed,
+/// and the error is returned once the running ones are done.
+///
+/// `f` also gets the progress bar, anything it prints has to go through [`ProgressBar::suspend`]
+/// so it doesn't garble the bar.
+///
+/// Returns the items that succeeded together with their result, in their original order.
+pub fn try_for_each<'a, T: Sync, R: Send>(
+    items: &'a [T],
+    jobs: usize,
+    failures: &mut Failures,
+    name: impl Fn(&T) -> &str,
+    f: impl Fn(&T, &ProgressBar) -> Result<R> + Sync,
+) -> Result<Vec<(&'a T, R)>> {
+    let items: Vec<&T> = items
+        .iter()
+        .filter(|&item| failures.selected(name(item)))
+        .collect();
+    let stop_on_error = !failures.keep_going();
+
+    let bar = ProgressBar::new(items.len() as u64);
+    let next = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+
+    thread::scope(|s| {
+        for _ in 0..jobs.clamp(1, items.len().max(1)) {
+            s.spawn(|| {
+                while !stop.load(Ordering::Relaxed) {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(&item) = items.get(i) else {
+                        break;
+                    };
+
+                    let res = f(item, &bar);
+                    if res.is_err() && stop_on_error {
+                        stop.store(true, Ordering::Relaxed);
+                    }
+
+                    results.lock().unwrap().push((i, res));
+                    bar.inc(1);
+                }
+            });
+        }
+    });
+    bar.finish();
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_by_key(|&(i, _)| i);
+
+    let mut succeeded = Vec::with_capacity(results.len());
+    for (i, res) in results {
+        if let Some(r) = failures.check(name(items[i]), res)? {
+            succeeded.push((items[i], r));
+        }
+    }
+
+    Ok(succeeded)
+}
+
+#[cfg(test)]
+mod tests {
+    use color_eyre::eyre::bail;
+
+    use super::try_for_each;
+    use crate::failures::Failures;
+
+    #[test]
+    fn keeps_order_and_records_failures() {
+        let items: Vec<String> = (0..20).map(|i| i.to_string()).collect();
+        let mut failures = Failures::new(true, None).unwrap();
+
+        let succeeded = try_for_each(&items, 4, &mut failures, String::as_str, |item, _| {
+            let n: u32 = item.parse()?;
+            if n % 5 == 0 {
+                bail!("{n} is divisible by 5");
+            }
+            Ok(n * 2)
+        })
+        .unwrap();
+
+        let doubled: Vec<u32> = succeeded.iter().map(|&(_, n)| n).collect();
+        assert_eq!(doubled.len(), 16);
+        assert!(doubled.windows(2).all(|w| w[0] < w[1]));
+        assert!(!failures.is_empty());
+    }
+}
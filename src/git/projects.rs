@@ -1,30 +1,49 @@
 use color_eyre::eyre::{Context as _, Result};
 use gitlab::{
     api::{
-        groups::projects::GroupProjects, ignore, paged, projects::{
-            fork::UnforkProject, protected_branches::{ProtectedBranches, UnprotectBranch}, repository::branches::{Branches, DeleteBranch}
-        }, ApiError, Query
+        groups::projects::GroupProjects,
+        ignore, paged,
+        projects::{
+            fork::UnforkProject,
+            protected_branches::{ProtectedBranches, UnprotectBranch},
+            repository::branches::{Branches, DeleteBranch},
+        },
+        ApiError, Client, Query,
     },
     Gitlab,
 };
-
-use indicatif::ProgressIterator;
+use indicatif::ProgressBar;
 use serde::Deserialize;
 
+use crate::failures::Failures;
+use crate::git::parallel;
 use crate::models::ProjectInfo;
 
-pub(crate) fn get_projects_by_group(client: &Gitlab, id: u64) -> Result<Vec<ProjectInfo>> {
+pub(crate) fn get_projects_by_group<C: Client>(client: &C, id: u64) -> Result<Vec<ProjectInfo>> {
     let endpoint = GroupProjects::builder().group(id).archived(false).build()?;
 
     Ok(paged(endpoint, gitlab::api::Pagination::All).query(client)?)
 }
 
+/// Like [`get_projects_by_group`], but also includes the projects in all subgroups
+pub(crate) fn get_projects_by_group_recursive<C: Client>(
+    client: &C,
+    id: u64,
+) -> Result<Vec<ProjectInfo>> {
+    let endpoint = GroupProjects::builder()
+        .group(id)
+        .archived(false)
+        .include_subgroups(true)
+        .build()?;
+
+    Ok(paged(endpoint, gitlab::api::Pagination::All).query(client)?)
+}
+
 pub fn list(client: &Gitlab, id: u64) -> Result<()> {
     let projects = get_projects_by_group(client, id)?;
 
     for project in projects {
-        let name = project.name.replace(' ', "-").to_lowercase();
-        println!("{name} {}", project.ssh_url_to_repo);
+        println!("{} {}", project.remote_name(), project.ssh_url_to_repo);
     }
 
     Ok(())
@@ -36,58 +55,109 @@ struct Branch {
     default: bool,
 }
 
-pub fn unprotect(client: &Gitlab, group: u64, branch: &str, dry_run: bool) -> Result<()> {
+pub fn unprotect<C: Client + Sync>(
+    client: &C,
+    group: u64,
+    branch: &str,
+    jobs: usize,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
     let projects = get_projects_by_group(client, group)?;
-    let mut n = 0;
 
-    for project in projects.into_iter().progress() {
-        let endpoint = ProtectedBranches::builder().project(project.id).build()?;
+    let results = parallel::try_for_each(
+        &projects,
+        jobs,
+        failures,
+        |p| p.name.as_str(),
+        |project, bar| unprotect_project(client, project, branch, bar, dry_run),
+    )?;
+
+    let n = results
+        .iter()
+        .filter(|&&(_, unprotected)| unprotected)
+        .count();
+    println!("Unprotected {branch} on {n} projects successfully");
 
-        let branches: Vec<Branch> = endpoint.query(client)?;
+    Ok(())
+}
 
-        if branches.iter().any(|b| b.name == branch) {
-            if dry_run {
-                println!("Dry Run: unprotected {branch} on {}", project.name);
-            } else {
-                let endpoint = UnprotectBranch::builder()
-                    .project(project.id)
-                    .name(branch)
-                    .build()?;
+/// Returns whether the branch was (or would have been) unprotected
+fn unprotect_project<C: Client>(
+    client: &C,
+    project: &ProjectInfo,
+    branch: &str,
+    bar: &ProgressBar,
+    dry_run: bool,
+) -> Result<bool> {
+    let endpoint = ProtectedBranches::builder().project(project.id).build()?;
 
-                ignore(endpoint).query(client)?;
-            }
+    let branches: Vec<Branch> = endpoint.query(client)?;
 
-            n += 1;
-        }
+    if !branches.iter().any(|b| b.name == branch) {
+        return Ok(false);
     }
-    println!("Unprotected {branch} on {n} projects successfully");
 
-    Ok(())
+    if dry_run {
+        bar.suspend(|| println!("Dry Run: unprotected {branch} on {}", project.name));
+    } else {
+        let endpoint = UnprotectBranch::builder()
+            .project(project.id)
+            .name(branch)
+            .build()?;
+
+        ignore(endpoint).query(client)?;
+    }
+
+    Ok(true)
 }
 
-pub fn remove_non_default_branches(client: &Gitlab, group: u64, dry_run: bool) -> Result<()> {
+pub fn remove_non_default_branches<C: Client + Sync>(
+    client: &C,
+    group: u64,
+    jobs: usize,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
     let projects = get_projects_by_group(client, group)?;
 
-    for project in projects.into_iter().progress() {
-        let endpoint = Branches::builder().project(project.id).build()?;
+    parallel::try_for_each(
+        &projects,
+        jobs,
+        failures,
+        |p| p.name.as_str(),
+        |project, bar| remove_project_branches(client, project, bar, dry_run),
+    )?;
 
-        let branches: Vec<Branch> = endpoint.query(client)?;
+    Ok(())
+}
+
+fn remove_project_branches<C: Client>(
+    client: &C,
+    project: &ProjectInfo,
+    bar: &ProgressBar,
+    dry_run: bool,
+) -> Result<()> {
+    let endpoint = Branches::builder().project(project.id).build()?;
 
-        for branch in branches {
-            if !branch.default {
-                if dry_run {
+    let branches: Vec<Branch> = endpoint.query(client)?;
+
+    for branch in branches {
+        if !branch.default {
+            if dry_run {
+                bar.suspend(|| {
                     println!(
                         "Dry Run: Deleting branch {} on {}",
                         branch.name, project.name
-                    );
-                } else {
-                    let endpoint = DeleteBranch::builder()
-                        .project(project.id)
-                        .branch(branch.name)
-                        .build()?;
-
-                    ignore(endpoint).query(client)?;
-                }
+                    )
+                });
+            } else {
+                let endpoint = DeleteBranch::builder()
+                    .project(project.id)
+                    .branch(branch.name)
+                    .build()?;
+
+                ignore(endpoint).query(client)?;
             }
         }
     }
@@ -95,25 +165,45 @@ pub fn remove_non_default_branches(client: &Gitlab, group: u64, dry_run: bool) -
     Ok(())
 }
 
-pub fn unfork(client: &Gitlab, group: u64, dry_run: bool) -> Result<()> {
+pub fn unfork<C: Client + Sync>(
+    client: &C,
+    group: u64,
+    jobs: usize,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
     let projects = get_projects_by_group(client, group)?;
-    for project in projects.into_iter().progress() {
-        if dry_run {
-            println!("Dry Run: Unforking project {}", project.id);
-            continue;
-        }
 
-        let endpoint = UnforkProject::builder()
-            .project(project.id)
-            .build()?;
+    parallel::try_for_each(
+        &projects,
+        jobs,
+        failures,
+        |p| p.name.as_str(),
+        |project, bar| unfork_project(client, project, bar, dry_run),
+    )?;
 
-        match ignore(endpoint).query(client) {
-            Ok(_) => {}
-            Err(ApiError::GitlabService { status, .. }) if status.as_u16() == 304 => {
-                // not a fork
-            }
-            e @ Err(_) => e.wrap_err("Error occured unforking")?,
+    Ok(())
+}
+
+fn unfork_project<C: Client>(
+    client: &C,
+    project: &ProjectInfo,
+    bar: &ProgressBar,
+    dry_run: bool,
+) -> Result<()> {
+    if dry_run {
+        bar.suspend(|| println!("Dry Run: Unforking project {}", project.id));
+        return Ok(());
+    }
+
+    let endpoint = UnforkProject::builder().project(project.id).build()?;
+
+    match ignore(endpoint).query(client) {
+        Ok(_) => Ok(()),
+        Err(ApiError::GitlabService { status, .. }) if status.as_u16() == 304 => {
+            // not a fork
+            Ok(())
         }
+        e @ Err(_) => e.wrap_err("Error occured unforking"),
     }
-    Ok(())
 }
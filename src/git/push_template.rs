@@ -0,0 +1,190 @@
+use std::{env, fs, path::Path};
+
+use color_eyre::eyre::{Context, Result};
+use gitlab::Gitlab;
+use indicatif::ProgressIterator;
+use itertools::Itertools;
+
+use crate::failures::Failures;
+use crate::git::{
+    local::{git, git_succeeds},
+    projects::get_projects_by_group,
+};
+use crate::models::ProjectInfo;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PushOutcome {
+    Pushed,
+    Merged,
+    UpToDate,
+    Conflicted,
+}
+
+/// Pushes `source` from a local template repository to `branch` of every project in a group
+///
+/// Without `merge` this is a plain push, which fails for projects that diverged from the template.
+/// With `merge` the template is merged into each project's branch in a temporary worktree first,
+/// projects for which that merge conflicts are left untouched.
+#[allow(clippy::too_many_arguments)]
+pub fn push_template(
+    client: &Gitlab,
+    template: &Path,
+    group: u64,
+    source: &str,
+    branch: &str,
+    merge: bool,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let projects = get_projects_by_group(client, group)?;
+
+    let mut outcomes: Vec<(&ProjectInfo, PushOutcome)> = Vec::new();
+
+    for project in projects.iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let res = if merge {
+            merge_and_push(template, project, source, branch, dry_run)
+        } else {
+            push(template, project, source, branch, dry_run)
+        };
+
+        match res {
+            Ok(outcome) => outcomes.push((project, outcome)),
+            Err(e) => failures.push(&project.name, e),
+        }
+    }
+
+    if dry_run {
+        println!("Dry Run: nothing was pushed");
+    }
+
+    for (outcome, label) in [
+        (PushOutcome::Pushed, "Pushed"),
+        (PushOutcome::Merged, "Merged"),
+        (PushOutcome::UpToDate, "Already up to date"),
+        (PushOutcome::Conflicted, "Conflicted"),
+    ] {
+        let names = outcomes
+            .iter()
+            .filter(|(_, o)| *o == outcome)
+            .map(|(p, _)| p.name.as_str())
+            .collect_vec();
+
+        if !names.is_empty() {
+            println!("{label} ({}): {}", names.len(), names.join(", "));
+        }
+    }
+
+    Ok(())
+}
+
+/// Fetches `branch` of a project and returns the commit it points to
+fn fetch(template: &Path, project: &ProjectInfo, branch: &str) -> Result<String> {
+    git(
+        template,
+        &["fetch", "--quiet", &project.ssh_url_to_repo, branch],
+    )?;
+
+    Ok(git(template, &["rev-parse", "FETCH_HEAD"])?
+        .trim()
+        .to_string())
+}
+
+fn push(
+    template: &Path,
+    project: &ProjectInfo,
+    source: &str,
+    branch: &str,
+    dry_run: bool,
+) -> Result<PushOutcome> {
+    let remote_head = fetch(template, project, branch)?;
+
+    if git_succeeds(
+        template,
+        &["merge-base", "--is-ancestor", source, &remote_head],
+    )? {
+        return Ok(PushOutcome::UpToDate);
+    }
+
+    let refspec = format!("{source}:refs/heads/{branch}");
+    let mut args = vec!["push", "--quiet"];
+    if dry_run {
+        args.push("--dry-run");
+    }
+    args.extend([project.ssh_url_to_repo.as_str(), refspec.as_str()]);
+
+    git(template, &args)?;
+
+    Ok(PushOutcome::Pushed)
+}
+
+fn merge_and_push(
+    template: &Path,
+    project: &ProjectInfo,
+    source: &str,
+    branch: &str,
+    dry_run: bool,
+) -> Result<PushOutcome> {
+    let remote_head = fetch(template, project, branch)?;
+
+    if git_succeeds(
+        template,
+        &["merge-base", "--is-ancestor", source, &remote_head],
+    )? {
+        return Ok(PushOutcome::UpToDate);
+    }
+
+    let worktree = env::temp_dir().join(format!("coursectl-merge-{}", project.id));
+    let worktree_str = worktree.to_string_lossy();
+
+    // a worktree left behind by an interrupted run would make `worktree add` fail
+    if worktree.exists() {
+        remove_worktree(template, &worktree_str);
+        if worktree.exists() {
+            fs::remove_dir_all(&worktree)
+                .wrap_err_with(|| format!("failed removing stale worktree {worktree_str}"))?;
+        }
+    }
+    git(template, &["worktree", "prune"])?;
+
+    git(
+        template,
+        &["worktree", "add", "--detach", &worktree_str, &remote_head],
+    )?;
+
+    let res = (|| -> Result<PushOutcome> {
+        if let Err(e) = git(&worktree, &["merge", "--no-edit", source]) {
+            let conflicts = git(&worktree, &["diff", "--name-only", "--diff-filter=U"])?;
+            if conflicts.trim().is_empty() {
+                return Err(e);
+            }
+
+            git(&worktree, &["merge", "--abort"])?;
+            return Ok(PushOutcome::Conflicted);
+        }
+
+        if !dry_run {
+            let refspec = format!("HEAD:refs/heads/{branch}");
+            git(
+                &worktree,
+                &["push", "--quiet", &project.ssh_url_to_repo, &refspec],
+            )?;
+        }
+
+        Ok(PushOutcome::Merged)
+    })();
+
+    remove_worktree(template, &worktree_str);
+
+    res
+}
+
+/// Removes a temporary worktree, failing to do so only leaves a directory behind so it is logged
+fn remove_worktree(template: &Path, worktree: &str) {
+    if let Err(e) = git(template, &["worktree", "remove", "--force", worktree]) {
+        eprintln!("Failed removing worktree {worktree}: {e:#}");
+    }
+}
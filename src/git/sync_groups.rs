@@ -0,0 +1,219 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::{Context, Result};
+use gitlab::{api::common::AccessLevel, Gitlab};
+use indicatif::ProgressIterator;
+use itertools::Itertools;
+
+use crate::failures::Failures;
+use crate::git::{
+    invite::{find_users, invite_by_email, invite_by_userinfo, UserInfo},
+    members::{edit_member, get_project_invitations, get_project_members, remove_member},
+    projects::get_projects_by_group,
+};
+use crate::models::{Group, ProjectInfo, ProjectMember, Student};
+
+/// What to do with members of a group repository who are no longer in the group
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RemovedAction {
+    /// Remove them from the repository
+    Remove,
+    /// Lower their access level
+    Downgrade,
+}
+
+struct ProjectDiff<'a> {
+    project: &'a ProjectInfo,
+    /// Students with a gitlab account who are not a member yet
+    add: Vec<UserInfo>,
+    /// Students without a gitlab account who have not been invited yet
+    invite: Vec<&'a Student>,
+    remove: Vec<ProjectMember>,
+}
+
+impl ProjectDiff<'_> {
+    fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.invite.is_empty() && self.remove.is_empty()
+    }
+}
+
+/// Brings the members of existing group repositories in line with the brightspace groups
+///
+/// Groups are matched to projects by name. Students are matched to gitlab users by username or
+/// e-mail like when they are invited, and to members by user id. Students without a gitlab account
+/// are matched to pending invitations by e-mail.
+/// Members with at least maintainer access and the token owner are never touched.
+///
+/// The full diff is printed first, and only applied after confirmation (unless `yes` is set).
+#[allow(clippy::too_many_arguments)]
+pub fn sync_group_members(
+    client: &Gitlab,
+    group: u64,
+    groups: &[Group],
+    token_user: &str,
+    access_level: AccessLevel,
+    removed: RemovedAction,
+    downgrade_to: AccessLevel,
+    failures: &mut Failures,
+    dry_run: bool,
+    yes: bool,
+) -> Result<()> {
+    let projects = get_projects_by_group(client, group)
+        .wrap_err("failed getting projects under given parent id")?;
+    let projects: HashMap<&str, &ProjectInfo> =
+        projects.iter().map(|p| (p.name.as_str(), p)).collect();
+
+    let mut diffs = Vec::new();
+    let mut without_repo = Vec::new();
+
+    for g in groups.iter().progress() {
+        let Some(&project) = projects.get(g.name.as_str()) else {
+            without_repo.push(g.name.as_str());
+            continue;
+        };
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let diff = diff_project(client, project, g, token_user, removed, downgrade_to)
+            .wrap_err("failed comparing members");
+
+        match failures.check(&project.name, diff)? {
+            Some(diff) if !diff.is_empty() => diffs.push(diff),
+            _ => {}
+        }
+    }
+
+    if !without_repo.is_empty() {
+        println!(
+            "{} groups have no repository: {}",
+            without_repo.len(),
+            without_repo.join(", ")
+        );
+    }
+
+    if diffs.is_empty() {
+        println!("All group repositories are up to date.");
+        return Ok(());
+    }
+
+    for diff in &diffs {
+        println!("{}:", diff.project.name);
+        for user in &diff.add {
+            println!("  + {}", user.username);
+        }
+        for student in &diff.invite {
+            println!("  + {} (invitation)", student.email);
+        }
+        for member in &diff.remove {
+            match removed {
+                RemovedAction::Remove => println!("  - {}", member.username),
+                RemovedAction::Downgrade => println!(
+                    "  ~ {} (access level {} -> {})",
+                    member.username,
+                    member.access_level,
+                    downgrade_to.as_u64()
+                ),
+            }
+        }
+    }
+
+    if dry_run {
+        println!("Dry Run: not applying changes to {} projects", diffs.len());
+        return Ok(());
+    }
+
+    if !yes && !confirm(&format!("Apply changes to {} projects?", diffs.len()))? {
+        println!("Aborted.");
+        return Ok(());
+    }
+
+    let mut n = 0;
+    for diff in diffs.iter().progress() {
+        match apply_diff(client, diff, access_level, removed, downgrade_to) {
+            Ok(()) => n += 1,
+            Err(e) => failures.push(&diff.project.name, e),
+        }
+    }
+
+    println!("Updated {n} projects successfully.");
+
+    Ok(())
+}
+
+fn diff_project<'a>(
+    client: &Gitlab,
+    project: &'a ProjectInfo,
+    group: &'a Group,
+    token_user: &str,
+    removed: RemovedAction,
+    downgrade_to: AccessLevel,
+) -> Result<ProjectDiff<'a>> {
+    let members = get_project_members(client, project.id)?;
+    let invitations = get_project_invitations(client, project.id)?;
+
+    let (users, mut invite) = find_users(client, &group.members.iter().collect_vec())?;
+
+    let remove = members
+        .iter()
+        .filter(|m| m.access_level < AccessLevel::Maintainer.as_u64() && m.username != token_user)
+        .filter(|m| !users.iter().any(|u| u.id == m.id))
+        .filter(|m| removed == RemovedAction::Remove || m.access_level > downgrade_to.as_u64())
+        .cloned()
+        .collect_vec();
+
+    let add = users
+        .into_iter()
+        .filter(|u| !members.iter().any(|m| m.id == u.id))
+        .collect_vec();
+
+    invite.retain(|s| {
+        !invitations
+            .iter()
+            .any(|i| i.invite_email.eq_ignore_ascii_case(&s.email))
+    });
+
+    Ok(ProjectDiff {
+        project,
+        add,
+        invite,
+        remove,
+    })
+}
+
+fn apply_diff(
+    client: &Gitlab,
+    diff: &ProjectDiff,
+    access_level: AccessLevel,
+    removed: RemovedAction,
+    downgrade_to: AccessLevel,
+) -> Result<()> {
+    invite_by_userinfo(client, diff.project.id, &diff.add, access_level)?;
+    invite_by_email(client, diff.project.id, &diff.invite, access_level)?;
+
+    for member in &diff.remove {
+        match removed {
+            RemovedAction::Remove => remove_member(client, diff.project.id, member.id)?,
+            RemovedAction::Downgrade => {
+                edit_member(client, diff.project.id, member.id, downgrade_to.as_u64())?
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Asks a yes/no question on the terminal, defaulting to no
+fn confirm(question: &str) -> Result<bool> {
+    print!("{question} [y/N] ");
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+use color_eyre::eyre::{bail, Context, Result};
+use gitlab::{
+    api::{common::AccessLevel, ignore, Query},
+    Gitlab,
+};
+use indicatif::ProgressIterator;
+use itertools::Itertools;
+
+use crate::failures::Failures;
+use crate::git::{
+    create_repos::repo_name,
+    endpoints::ArchiveProject,
+    invite::find_users,
+    members::{delete_invitation, get_project_invitations, get_project_members, remove_member},
+    projects::get_projects_by_group,
+};
+use crate::models::{ProjectInfo, ProjectMember, Student};
+
+/// What to do with the repositories of students who are no longer enrolled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DroppedAction {
+    /// Only list the repositories
+    Report,
+    /// Remove the student members and pending invitations from the repositories
+    Revoke,
+    /// Archive the repositories, making them read-only
+    Archive,
+}
+
+/// Compares the individual repositories in a group against the list of enrolled students
+///
+/// Repositories of students who are no longer enrolled are handled according to `action`,
+/// members with at least maintainer access and the token owner are never removed.
+/// The members of the repositories of enrolled students are compared against the roster as well,
+/// members who are not enrolled are only reported, as they may be teaching assistants.
+/// Students are matched to gitlab users by username or e-mail, like when they are invited.
+///
+/// Only projects named with `repo_name_prefix` are considered, which is required for any action
+/// other than [`DroppedAction::Report`].
+#[allow(clippy::too_many_arguments)]
+pub fn sync_roster(
+    client: &Gitlab,
+    students: &[Student],
+    repo_name_prefix: &Option<String>,
+    group: u64,
+    token_user: &str,
+    action: DroppedAction,
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let prefix = match repo_name_prefix {
+        Some(p) => Some(format!("{p} - ")),
+        None if action == DroppedAction::Report => None,
+        None => bail!(
+            "--prefix is required to revoke or archive repositories, \
+            without it every other project in the group counts as dropped"
+        ),
+    };
+
+    let expected: HashSet<String> = students
+        .iter()
+        .map(|s| repo_name(repo_name_prefix, &s.netid))
+        .collect();
+
+    let projects = get_projects_by_group(client, group)
+        .wrap_err("failed getting projects under given parent id")?;
+
+    let (enrolled, dropped): (Vec<&ProjectInfo>, Vec<&ProjectInfo>) = projects
+        .iter()
+        .filter(|p| {
+            prefix
+                .as_ref()
+                .map_or(true, |prefix| p.name.starts_with(prefix))
+        })
+        .partition(|p| expected.contains(&p.name));
+
+    let existing: HashSet<&str> = projects.iter().map(|p| p.name.as_str()).collect();
+    let missing = students
+        .iter()
+        .filter(|s| !existing.contains(repo_name(repo_name_prefix, &s.netid).as_str()))
+        .map(|s| s.netid.as_str())
+        .collect_vec();
+
+    if !missing.is_empty() {
+        println!(
+            "{} enrolled students have no repository: {}",
+            missing.len(),
+            missing.join(", ")
+        );
+    }
+
+    let enrolled = enrolled
+        .into_iter()
+        .filter(|p| failures.selected(&p.name))
+        .collect_vec();
+
+    let user_ids: HashSet<u64> = if enrolled.is_empty() {
+        HashSet::new()
+    } else {
+        let (users, _) = find_users(client, &students.iter().collect_vec())
+            .wrap_err("failed finding the gitlab users of enrolled students")?;
+        users.into_iter().map(|u| u.id).collect()
+    };
+
+    let mut unenrolled = Vec::new();
+    for project in enrolled.into_iter().progress() {
+        match unenrolled_members(client, project, &user_ids, token_user) {
+            Ok(members) => unenrolled.extend(members.into_iter().map(|m| (project, m))),
+            Err(e) => failures.push(&project.name, e),
+        }
+    }
+
+    if !unenrolled.is_empty() {
+        println!(
+            "{} members of the repositories of enrolled students are not enrolled:",
+            unenrolled.len()
+        );
+        for (project, member) in &unenrolled {
+            println!("  {}: {}", project.name, member.username);
+        }
+    }
+
+    if dropped.is_empty() {
+        println!("All repositories belong to enrolled students.");
+        return Ok(());
+    }
+
+    println!(
+        "{} repositories belong to students who are no longer enrolled: {}",
+        dropped.len(),
+        dropped.iter().map(|p| p.name.as_str()).join(", ")
+    );
+
+    if action == DroppedAction::Report {
+        return Ok(());
+    }
+
+    for project in dropped.into_iter().progress() {
+        if !failures.selected(&project.name) {
+            continue;
+        }
+
+        let res = match action {
+            DroppedAction::Report => Ok(()),
+            DroppedAction::Revoke => revoke(client, project, token_user, dry_run),
+            DroppedAction::Archive => archive(client, project, dry_run),
+        };
+
+        if let Err(e) = res {
+            failures.push(&project.name, e);
+        }
+    }
+
+    Ok(())
+}
+
+/// The members of `project` below maintainer who are not one of the gitlab users in `user_ids`
+fn unenrolled_members(
+    client: &Gitlab,
+    project: &ProjectInfo,
+    user_ids: &HashSet<u64>,
+    token_user: &str,
+) -> Result<Vec<ProjectMember>> {
+    Ok(get_project_members(client, project.id)?
+        .into_iter()
+        .filter(|m| m.access_level < AccessLevel::Maintainer.as_u64() && m.username != token_user)
+        .filter(|m| !user_ids.contains(&m.id))
+        .collect())
+}
+
+fn revoke(client: &Gitlab, project: &ProjectInfo, token_user: &str, dry_run: bool) -> Result<()> {
+    let members = get_project_members(client, project.id)?;
+
+    for member in members {
+        if member.access_level >= AccessLevel::Maintainer.as_u64() || member.username == token_user
+        {
+            continue;
+        }
+
+        if dry_run {
+            println!(
+                "Dry Run: removing {} from {}",
+                member.username, project.name
+            );
+        } else {
+            remove_member(client, project.id, member.id)?;
+        }
+    }
+
+    for invitation in get_project_invitations(client, project.id)? {
+        if dry_run {
+            println!(
+                "Dry Run: deleting invitation of {} to {}",
+                invitation.invite_email, project.name
+            );
+        } else {
+            delete_invitation(client, project.id, &invitation.invite_email)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn archive(client: &Gitlab, project: &ProjectInfo, dry_run: bool) -> Result<()> {
+    if dry_run {
+        println!("Dry Run: archiving {}", project.name);
+        return Ok(());
+    }
+
+    ignore(ArchiveProject {
+        project: project.id,
+    })
+    .query(client)
+    .wrap_err("failed archiving project")
+}
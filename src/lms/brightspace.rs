@@ -0,0 +1,80 @@
+use std::collections::HashMap;
+
+use color_eyre::Result;
+
+use super::{GradeItem, GroupCategory, Lms, LmsStudent, StudentFilter};
+use crate::brightspace::Client;
+use crate::models::Group;
+
+impl Lms for Client {
+    fn students(&self, course: u64, filter: &StudentFilter) -> Result<Vec<LmsStudent>> {
+        Ok(self
+            .get_enrolled_students(course, filter)?
+            .into_iter()
+            .map(|(enrollment, student)| LmsStudent {
+                id: enrollment.identifier,
+                student,
+                sections: enrollment.sections,
+                role: enrollment.role_name,
+                enrollment_date: enrollment.enrollment_date,
+            })
+            .collect())
+    }
+
+    fn group_categories(&self, course: u64) -> Result<Vec<GroupCategory>> {
+        Ok(self
+            .get_group_categories(course)?
+            .into_iter()
+            .map(|c| GroupCategory {
+                id: c.group_category_id,
+                name: c.name,
+                groups: c.groups.len(),
+            })
+            .collect())
+    }
+
+    fn groups(&self, course: u64, category: u64) -> Result<Vec<Group>> {
+        self.get_groups(course, category)
+    }
+
+    fn grade_items(&self, course: u64) -> Result<Vec<GradeItem>> {
+        Ok(self
+            .get_grade_objects(course)?
+            .into_iter()
+            .map(|g| GradeItem {
+                id: g.id,
+                name: g.name,
+            })
+            .collect())
+    }
+
+    fn user_grades(&self, course: u64, user: &str) -> Result<HashMap<u64, String>> {
+        Ok(self
+            .get_user_grade_values(course, user)?
+            .into_iter()
+            .filter_map(|v| {
+                let value = v
+                    .points_numerator
+                    .map_or_else(|| v.displayed_grade.clone(), |p| p.to_string());
+                Some((v.grade_object_identifier.parse().ok()?, value))
+            })
+            .collect())
+    }
+
+    fn grade(&self, course: u64, item: u64, user: &str) -> Result<Option<f64>> {
+        Ok(self
+            .get_grade_value(course, item, user)?
+            .and_then(|v| v.points_numerator))
+    }
+
+    fn set_grade(
+        &self,
+        course: u64,
+        item: u64,
+        user: &str,
+        score: f64,
+        feedback: Option<&str>,
+    ) -> Result<()> {
+        self.put_grade_value(course, item, user, score, feedback)
+    }
+}
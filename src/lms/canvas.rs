@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+
+use color_eyre::{eyre::Context, Result};
+use serde::de::DeserializeOwned;
+
+use super::{GradeItem, GroupCategory, Lms, LmsStudent, Role, StudentFilter};
+use crate::models::{
+    CanvasAssignment, CanvasGroup, CanvasGroupCategory, CanvasSection, CanvasSubmission,
+    CanvasUser, Enrollment, Group, Student,
+};
+use crate::netid::{self, NetidMapping};
+
+/// Enrollment type of students
+const STUDENT_ENROLLMENT: &str = "StudentEnrollment";
+
+/// Client for the Canvas REST API, authenticated with an access token
+///
+/// See <https://canvas.instructure.com/doc/api/>
+#[derive(Debug)]
+pub struct Canvas {
+    base_url: String,
+    token: String,
+    netids: NetidMapping,
+}
+
+impl Canvas {
+    /// Login ids without a domain are taken as netids, in addition to the rules of `netids`
+    pub fn new(base_url: &str, token: String, netids: NetidMapping) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            netids: NetidMapping {
+                bare_usernames: true,
+                ..netids
+            },
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/v1/{path}", self.base_url)
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        ureq::request(method, url).set("Authorization", &format!("Bearer {}", self.token))
+    }
+
+    fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T> {
+        Ok(self.request("GET", &self.url(path)).call()?.into_json()?)
+    }
+
+    /// Retrieves all items of a list endpoint, following the `next` links of the responses
+    ///
+    /// See <https://canvas.instructure.com/doc/api/file.pagination.html>
+    fn get_paged<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut next = Some(format!("{}{separator}per_page=100", self.url(path)));
+        let mut items = Vec::new();
+
+        while let Some(url) = next {
+            let res = self.request("GET", &url).call()?;
+            next = res.header("Link").and_then(next_link);
+            items.extend(res.into_json::<Vec<T>>()?);
+        }
+
+        Ok(items)
+    }
+}
+
+/// Extracts the url of the next page from a `Link` header
+fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let (url, params) = link.split_once(';')?;
+        params
+            .split(';')
+            .any(|p| p.trim() == r#"rel="next""#)
+            .then(|| {
+                url.trim()
+                    .trim_start_matches('<')
+                    .trim_end_matches('>')
+                    .to_string()
+            })
+    })
+}
+
+impl Lms for Canvas {
+    fn students(&self, course: u64, filter: &StudentFilter) -> Result<Vec<LmsStudent>> {
+        let filter = filter.or_roles(vec![Role::Name(STUDENT_ENROLLMENT.to_string())]);
+
+        let sections: HashMap<u64, String> = self
+            .get_paged::<CanvasSection>(&format!("courses/{course}/sections"))
+            .wrap_err("failed getting sections from canvas")?
+            .into_iter()
+            .map(|s| (s.id, s.name))
+            .collect();
+
+        let users: Vec<CanvasUser> = self
+            .get_paged(&format!(
+                "courses/{course}/users?include[]=enrollments&include[]=email"
+            ))
+            .wrap_err("failed getting users from canvas")?;
+
+        let mut students = Vec::new();
+        let mut unmapped = Vec::new();
+        for user in users {
+            let user_sections: Vec<String> = user
+                .enrollments
+                .iter()
+                .filter_map(|e| sections.get(&e.course_section_id?).cloned())
+                .collect();
+            let enrollment_date = user.enrollments.iter().filter_map(|e| e.created_at).min();
+
+            // users can be enrolled multiple times, for example in multiple sections or roles
+            let enrollment = user.enrollments.iter().find_map(|e| {
+                let enrollment = Enrollment {
+                    identifier: user.id.to_string(),
+                    display_name: user.name.clone(),
+                    role_id: e.role_id,
+                    role_name: e.role.clone(),
+                    sections: user_sections.clone(),
+                    enrollment_date,
+                };
+                filter
+                    .matches(&enrollment, &e.enrollment_type)
+                    .then_some(enrollment)
+            });
+            let Some(enrollment) = enrollment else {
+                continue;
+            };
+
+            let netid = self
+                .netids
+                .netid(user.login_id.as_deref(), user.email.as_deref());
+            let (Some(netid), Some(email)) = (netid, user.email) else {
+                unmapped.push(format!(
+                    "{} ({})",
+                    user.name,
+                    user.login_id.as_deref().unwrap_or("no login id")
+                ));
+                continue;
+            };
+
+            students.push(LmsStudent {
+                id: enrollment.identifier,
+                student: Student {
+                    netid,
+                    student_number: user.sis_user_id.and_then(|id| id.parse().ok()),
+                    email,
+                },
+                sections: enrollment.sections,
+                role: enrollment.role_name,
+                enrollment_date: enrollment.enrollment_date,
+            });
+        }
+
+        netid::skip_unmapped(unmapped);
+        Ok(students)
+    }
+
+    fn group_categories(&self, course: u64) -> Result<Vec<GroupCategory>> {
+        let categories: Vec<CanvasGroupCategory> =
+            self.get_paged(&format!("courses/{course}/group_categories"))?;
+
+        categories
+            .into_iter()
+            .map(|c| {
+                let groups: Vec<CanvasGroup> =
+                    self.get_paged(&format!("group_categories/{}/groups", c.id))?;
+                Ok(GroupCategory {
+                    id: c.id,
+                    name: c.name,
+                    groups: groups.len(),
+                })
+            })
+            .collect()
+    }
+
+    fn groups(&self, course: u64, category: u64) -> Result<Vec<Group>> {
+        let groups: Vec<CanvasGroup> = self
+            .get_paged(&format!("group_categories/{category}/groups"))
+            .wrap_err("failed getting groups from canvas")?;
+
+        let students: HashMap<String, Student> = self
+            .students(course, &StudentFilter::default())?
+            .into_iter()
+            .map(|s| (s.id, s.student))
+            .collect();
+
+        groups
+            .into_iter()
+            .map(|g| {
+                let users: Vec<CanvasUser> = self
+                    .get_paged(&format!("groups/{}/users", g.id))
+                    .wrap_err_with(|| format!("failed getting members of {}", g.name))?;
+
+                let members = users
+                    .iter()
+                    .filter_map(|u| {
+                        let student = students.get(&u.id.to_string()).cloned();
+                        if student.is_none() {
+                            eprintln!(
+                                "Skipping {} in {}, not a student in the course",
+                                u.name, g.name
+                            );
+                        }
+                        student
+                    })
+                    .collect();
+
+                Ok(Group {
+                    name: g.name.replace(' ', ""),
+                    members,
+                })
+            })
+            .collect()
+    }
+
+    fn grade_items(&self, course: u64) -> Result<Vec<GradeItem>> {
+        let assignments: Vec<CanvasAssignment> =
+            self.get_paged(&format!("courses/{course}/assignments"))?;
+
+        Ok(assignments
+            .into_iter()
+            .map(|a| GradeItem {
+                id: a.id,
+                name: a.name,
+            })
+            .collect())
+    }
+
+    fn user_grades(&self, course: u64, user: &str) -> Result<HashMap<u64, String>> {
+        let submissions: Vec<CanvasSubmission> = self.get_paged(&format!(
+            "courses/{course}/students/submissions?student_ids[]={user}"
+        ))?;
+
+        Ok(submissions
+            .into_iter()
+            .filter_map(|s| {
+                let value = s.score.map(|p| p.to_string()).or(s.grade)?;
+                Some((s.assignment_id, value))
+            })
+            .collect())
+    }
+
+    fn grade(&self, course: u64, item: u64, user: &str) -> Result<Option<f64>> {
+        let submission: CanvasSubmission = self.get(&format!(
+            "courses/{course}/assignments/{item}/submissions/{user}"
+        ))?;
+
+        Ok(submission.score)
+    }
+
+    fn set_grade(
+        &self,
+        course: u64,
+        item: u64,
+        user: &str,
+        score: f64,
+        feedback: Option<&str>,
+    ) -> Result<()> {
+        let url = self.url(&format!(
+            "courses/{course}/assignments/{item}/submissions/{user}"
+        ));
+        let score = score.to_string();
+
+        let mut form = vec![("submission[posted_grade]", score.as_str())];
+        if let Some(feedback) = feedback {
+            // every comment is added to the submission, so only add it if it is new
+            let submission: CanvasSubmission = self.get(&format!(
+                "courses/{course}/assignments/{item}/submissions/{user}?include[]=submission_comments"
+            ))?;
+            let unchanged = submission
+                .submission_comments
+                .last()
+                .is_some_and(|c| c.comment == feedback);
+
+            if !unchanged {
+                form.push(("comment[text_comment]", feedback));
+            }
+        }
+
+        self.request("PUT", &url)
+            .send_form(&form)
+            .wrap_err("failed setting grade")?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_link;
+
+    #[test]
+    fn parses_link_header() {
+        let header = r#"<https://canvas.example.com/api/v1/courses/1/users?page=2&per_page=100>; rel="next", <https://canvas.example.com/api/v1/courses/1/users?page=1&per_page=100>; rel="first""#;
+
+        assert_eq!(
+            next_link(header).as_deref(),
+            Some("https://canvas.example.com/api/v1/courses/1/users?page=2&per_page=100")
+        );
+        assert_eq!(
+            next_link(r#"<https://canvas.example.com/api/v1/x?page=1>; rel="first""#),
+            None
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use std::{collections::HashMap, io};
+
+use color_eyre::{eyre::Context, Result};
+use indicatif::ProgressIterator;
+
+use super::{Lms, StudentFilter};
+use crate::failures::Failures;
+use crate::models::{GradeRecord, Group};
+
+/// Writes the grades of all students as CSV, with a row per student and a column per grade item
+pub fn write_grades_csv(lms: &dyn Lms, course: u64, out: impl io::Write) -> Result<()> {
+    let items = lms
+        .grade_items(course)
+        .wrap_err("failed getting grade items")?;
+    let students = lms.students(course, &StudentFilter::default())?;
+
+    let mut wtr = csv::Writer::from_writer(out);
+    wtr.write_record(std::iter::once("netid").chain(items.iter().map(|g| g.name.as_str())))?;
+
+    for s in students.iter().progress() {
+        let values = lms
+            .user_grades(course, &s.id)
+            .wrap_err_with(|| format!("failed getting grades of {}", s.student.netid))?;
+
+        let row = items
+            .iter()
+            .map(|g| values.get(&g.id).cloned().unwrap_or_default());
+
+        wtr.write_record(std::iter::once(s.student.netid.clone()).chain(row))?;
+    }
+
+    wtr.flush()?;
+
+    Ok(())
+}
+
+/// Turns grades of projects into grades of students
+///
+/// Records named after a group get expanded into a record for every member of that group,
+/// otherwise the repository prefix (if any) is stripped to get the netid.
+pub fn expand_grade_records(
+    records: Vec<GradeRecord>,
+    repo_name_prefix: &Option<String>,
+    groups: &[Group],
+) -> Vec<GradeRecord> {
+    let prefix = repo_name_prefix.as_ref().map(|p| format!("{p} - "));
+
+    records
+        .into_iter()
+        .flat_map(|record| {
+            if let Some(group) = groups.iter().find(|g| g.name == record.netid) {
+                return group
+                    .members
+                    .iter()
+                    .map(|s| GradeRecord {
+                        netid: s.netid.clone(),
+                        ..record.clone()
+                    })
+                    .collect();
+            }
+
+            let netid = prefix
+                .as_deref()
+                .and_then(|p| record.netid.strip_prefix(p))
+                .unwrap_or(&record.netid)
+                .to_string();
+
+            vec![GradeRecord { netid, ..record }]
+        })
+        .collect()
+}
+
+/// Writes the given grades to a grade item, users are resolved by netid through the list of students.
+///
+/// With `dry_run` the current and new grade of every student are printed instead.
+pub fn push_grades(
+    lms: &dyn Lms,
+    course: u64,
+    item: u64,
+    grades: &[GradeRecord],
+    failures: &mut Failures,
+    dry_run: bool,
+) -> Result<()> {
+    let identifiers: HashMap<String, String> = lms
+        .students(course, &StudentFilter::default())?
+        .into_iter()
+        .map(|s| (s.student.netid, s.id))
+        .collect();
+
+    let mut unknown = Vec::new();
+    let mut n = 0;
+
+    for grade in grades.iter().progress() {
+        if !failures.selected(&grade.netid) {
+            continue;
+        }
+
+        let Some(user) = identifiers.get(&grade.netid) else {
+            unknown.push(grade.netid.as_str());
+            continue;
+        };
+
+        let res = if dry_run {
+            lms.grade(course, item, user).map(|old| {
+                let old = old.map_or_else(|| "-".to_string(), |p| p.to_string());
+                println!("Dry Run: {}: {old} -> {}", grade.netid, grade.score);
+            })
+        } else {
+            lms.set_grade(course, item, user, grade.score, grade.feedback.as_deref())
+        };
+
+        match res {
+            Ok(()) => n += 1,
+            Err(e) => failures.push(&grade.netid, e),
+        }
+    }
+
+    if !dry_run {
+        println!("Pushed {n} grades successfully.");
+    }
+
+    if !unknown.is_empty() {
+        eprintln!(
+            "{} students are not in the course: {}",
+            unknown.len(),
+            unknown.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_grade_records;
+    use crate::models::{GradeRecord, Group, Student};
+
+    fn record(netid: &str, score: f64) -> GradeRecord {
+        GradeRecord {
+            netid: netid.to_string(),
+            score,
+            feedback: None,
+        }
+    }
+
+    #[test]
+    fn expands_groups_and_prefixes() {
+        let student = |netid: &str| Student {
+            netid: netid.to_string(),
+            student_number: None,
+            email: format!("{netid}@example.com"),
+        };
+        let groups = vec![Group {
+            name: "Group01".to_string(),
+            members: vec![student("alice"), student("bob")],
+        }];
+
+        let records = vec![record("Group01", 8.0), record("RTS - carol", 6.5)];
+        let expanded = expand_grade_records(records, &Some("RTS".to_string()), &groups);
+
+        assert_eq!(
+            expanded,
+            vec![
+                record("alice", 8.0),
+                record("bob", 8.0),
+                record("carol", 6.5)
+            ]
+        );
+    }
+}
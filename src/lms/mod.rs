@@ -0,0 +1,196 @@
+//! Operations on the roster, groups and grades of a course, independent of the learning management system
+
+use std::{collections::HashMap, convert::Infallible, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use color_eyre::Result;
+
+use crate::models::{Enrollment, Group, Student};
+
+mod brightspace;
+pub mod canvas;
+pub mod grades;
+
+/// The learning management systems coursectl can talk to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LmsKind {
+    Brightspace,
+    Canvas,
+}
+
+/// A learning management system, like brightspace or canvas
+///
+/// Courses, group categories and grade items are identified by their numeric id,
+/// users by the (string) identifier the LMS uses for them.
+pub trait Lms {
+    /// Students of a course, with their identifier and the sections they are in
+    fn students(&self, course: u64, filter: &StudentFilter) -> Result<Vec<LmsStudent>>;
+
+    fn group_categories(&self, course: u64) -> Result<Vec<GroupCategory>>;
+
+    /// Groups of a group category, with their members
+    fn groups(&self, course: u64, category: u64) -> Result<Vec<Group>>;
+
+    fn grade_items(&self, course: u64) -> Result<Vec<GradeItem>>;
+
+    /// Grades of a user, by grade item id
+    fn user_grades(&self, course: u64, user: &str) -> Result<HashMap<u64, String>>;
+
+    /// Score of a user for a grade item, or `None` if they have not been graded yet
+    fn grade(&self, course: u64, item: u64, user: &str) -> Result<Option<f64>>;
+
+    fn set_grade(
+        &self,
+        course: u64,
+        item: u64,
+        user: &str,
+        score: f64,
+        feedback: Option<&str>,
+    ) -> Result<()>;
+}
+
+/// A student in a course
+#[derive(Debug, Clone)]
+pub struct LmsStudent {
+    /// Identifier of the user in the LMS
+    pub id: String,
+    pub student: Student,
+    /// Names of the sections the student is in
+    pub sections: Vec<String>,
+    /// Name of the role the student is enrolled with
+    pub role: String,
+    pub enrollment_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GroupCategory {
+    pub id: u64,
+    pub name: String,
+    /// Number of groups in the category
+    pub groups: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct GradeItem {
+    pub id: u64,
+    pub name: String,
+}
+
+/// A role in a course, selected by its id or by its name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+    Id(u64),
+    /// Either the role name or the display name of the role, compared case-insensitively
+    Name(String),
+}
+
+impl FromStr for Role {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(id) => Role::Id(id),
+            Err(_) => Role::Name(s.to_string()),
+        })
+    }
+}
+
+impl Role {
+    pub fn matches(&self, id: u64, names: &[&str]) -> bool {
+        match self {
+            Role::Id(i) => *i == id,
+            Role::Name(name) => names.iter().any(|n| n.eq_ignore_ascii_case(name)),
+        }
+    }
+}
+
+/// Selects which of the enrolled users are returned by [`Lms::students`]
+#[derive(Debug, Clone, Default)]
+pub struct StudentFilter {
+    /// Roles to include, the student role of the LMS when empty
+    pub roles: Vec<Role>,
+    /// Names of the sections to include, every section when empty
+    pub sections: Vec<String>,
+    /// Only include users who enrolled after this moment
+    pub enrolled_after: Option<DateTime<Utc>>,
+}
+
+impl StudentFilter {
+    /// Uses `roles` if no roles are selected
+    pub fn or_roles(&self, roles: Vec<Role>) -> Self {
+        let mut filter = self.clone();
+        if filter.roles.is_empty() {
+            filter.roles = roles;
+        }
+        filter
+    }
+
+    /// Whether an enrollment is selected, `display_name` is an alternative name of its role
+    pub fn matches(&self, enrollment: &Enrollment, display_name: &str) -> bool {
+        let names = [enrollment.role_name.as_str(), display_name];
+
+        self.roles
+            .iter()
+            .any(|r| r.matches(enrollment.role_id, &names))
+            && (self.sections.is_empty()
+                || enrollment
+                    .sections
+                    .iter()
+                    .any(|s| self.sections.contains(s)))
+            && self.enrolled_after.map_or(true, |after| {
+                enrollment.enrollment_date.is_some_and(|date| date > after)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::{Role, StudentFilter};
+    use crate::models::Enrollment;
+
+    fn enrollment(role: u64, section: &str, day: u32) -> Enrollment {
+        Enrollment {
+            identifier: "1".to_string(),
+            display_name: "Doe, John".to_string(),
+            role_id: role,
+            role_name: "Student".to_string(),
+            sections: vec![section.to_string()],
+            enrollment_date: Some(Utc.with_ymd_and_hms(2024, 9, day, 0, 0, 0).unwrap()),
+        }
+    }
+
+    #[test]
+    fn filters_on_role_section_and_date() {
+        let filter = StudentFilter {
+            sections: vec!["Lab A".to_string()],
+            enrolled_after: Some(Utc.with_ymd_and_hms(2024, 9, 10, 0, 0, 0).unwrap()),
+            ..Default::default()
+        }
+        .or_roles(vec![Role::Id(110)]);
+
+        assert!(filter.matches(&enrollment(110, "Lab A", 15), "Student"));
+        assert!(!filter.matches(&enrollment(109, "Lab A", 15), "Student"));
+        assert!(!filter.matches(&enrollment(110, "Lab B", 15), "Student"));
+        assert!(!filter.matches(&enrollment(110, "Lab A", 5), "Student"));
+    }
+
+    #[test]
+    fn filters_on_role_names() {
+        let filter = StudentFilter {
+            roles: vec![
+                "110".parse().unwrap(),
+                "teaching assistant".parse().unwrap(),
+            ],
+            ..Default::default()
+        }
+        .or_roles(vec![Role::Id(109)]);
+        assert_eq!(filter.roles[0], Role::Id(110));
+
+        assert!(filter.matches(&enrollment(110, "Lab A", 15), "Student"));
+        assert!(filter.matches(&enrollment(118, "Lab A", 15), "Teaching Assistant"));
+        assert!(!filter.matches(&enrollment(109, "Lab A", 15), "Instructor"));
+    }
+}
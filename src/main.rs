@@ -1,16 +1,40 @@
 #![allow(dead_code)]
-use std::{fs::File, path::PathBuf};
+use std::{collections::HashMap, fs::File, path::PathBuf};
 
-use clap::{Args, Parser, Subcommand};
-use color_eyre::eyre::{Context, ContextCompat, Result};
-use git::create_repos;
+use chrono::{DateTime, Utc};
+use clap::{Args, CommandFactory, FromArgMatches, Parser, Subcommand};
+use color_eyre::eyre::{bail, Context, ContextCompat, Result};
+use git::{
+    client::RateLimitedClient,
+    clone, collect, create_repos,
+    deadline::{self, ReportFormat},
+    freeze,
+    journal::Journal,
+    merge_requests, push_template,
+    sync_groups::{self, RemovedAction},
+    sync_roster::{self, DroppedAction},
+};
 use gitlab::{api::common::AccessLevel, Gitlab};
 
+use crate::brightspace::{
+    dropbox,
+    oauth::{self, AuthMode},
+};
+use crate::failures::Failures;
 use crate::git::projects;
+use crate::lms::{canvas::Canvas, grades, Lms, LmsKind, Role, StudentFilter};
+use crate::models::{GradeRecord, Group, Student};
+use crate::netid::NetidMapping;
+use crate::roster::{FileRoster, LmsRoster, Roster, StudentRecord};
 
 mod brightspace;
+mod config;
+mod failures;
 mod git;
+mod lms;
 mod models;
+mod netid;
+mod roster;
 
 #[derive(Debug, Parser)]
 struct Cli {
@@ -18,6 +42,29 @@ struct Cli {
     #[arg(long, default_value_t = false)]
     dry_run: bool,
 
+    /// Course from `coursectl.toml` to take default arguments from
+    #[arg(long, global = true, env = "COURSECTL_COURSE")]
+    course: Option<String>,
+
+    /// Keep processing the other projects or students when one fails, instead of stopping
+    #[arg(long, global = true, default_value_t = false)]
+    keep_going: bool,
+
+    /// Write the projects or students that failed to this CSV file
+    #[arg(long, global = true)]
+    failures_file: Option<PathBuf>,
+
+    /// Only process the projects or students listed in a file written by --failures-file
+    #[arg(long, global = true)]
+    retry_from: Option<PathBuf>,
+
+    /// Number of projects to work on in parallel
+    ///
+    /// Used by create-individual-repos, create-group-repos, unprotect, unfork and
+    /// remove-non-default-branches.
+    #[arg(short, long, global = true, default_value_t = 1)]
+    jobs: usize,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -34,6 +81,110 @@ enum Commands {
         gitlab: GitlabArgs,
     },
 
+    /// Clone all projects in a given group, updating the ones that were already cloned
+    CloneAll {
+        /// The group id to clone the projects of
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// Directory to clone the projects into
+        #[arg(short, long = "out", default_value = "student_solutions")]
+        output_dir: PathBuf,
+
+        /// Branch to check out, defaults to the default branch of every project
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// Push a local template repository to all projects in a given group
+    PushTemplate {
+        /// The group id of the projects to push the template to
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// Path to a local clone of the template repository
+        #[arg(short, long = "template", default_value = ".")]
+        template_dir: PathBuf,
+
+        /// Branch in the student repositories to push to
+        #[arg(short, long, default_value = "main")]
+        branch: String,
+
+        /// Ref in the template repository to push, defaults to the target branch
+        #[arg(short, long)]
+        source: Option<String>,
+
+        /// Merge the template into every repository before pushing, instead of doing a plain push
+        #[arg(short, long, default_value_t = false)]
+        merge: bool,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// Push a branch to all projects in a given group and open a merge request for it
+    CreateFixMr {
+        /// The group id of the projects to open the merge requests in
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// Path to a local clone of the template repository containing the source branch
+        #[arg(long = "template", default_value = ".")]
+        template_dir: PathBuf,
+
+        /// Branch containing the fix
+        #[arg(short, long = "source", required = true)]
+        source_branch: String,
+
+        /// Branch to merge the fix into
+        #[arg(long = "target", default_value = "main")]
+        target_branch: String,
+
+        /// Title of the merge request
+        #[arg(short, long, required = true)]
+        title: String,
+
+        /// Description of the merge request
+        #[arg(short, long)]
+        description: Option<String>,
+
+        /// Remove the source branch once the merge request is merged
+        #[arg(long, default_value_t = false)]
+        remove_source_branch: bool,
+
+        /// Gitlab user id to assign the merge requests to
+        #[arg(long)]
+        assignee: Option<u64>,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// Download all files matching a pattern from every project in a given group
+    Collect {
+        /// The group id of the projects to collect the files from
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// Glob patterns of the files to collect, e.g. "*.pdf"
+        #[arg(required = true)]
+        patterns: Vec<String>,
+
+        /// Branch or tag to collect the files from, defaults to the default branch of every project
+        #[arg(short, long = "ref")]
+        ref_: Option<String>,
+
+        /// Directory to write the collected files to
+        #[arg(short, long = "out", default_value = "collected")]
+        output_dir: PathBuf,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
     /// Unprotect all branches within a certain group
     Unprotect {
         /// The group id to unprotect the branches for
@@ -47,6 +198,111 @@ enum Commands {
         gitlab: GitlabArgs,
     },
 
+    /// Lower the access level of all students and pending invitations in all projects within a
+    /// certain group, for example at a deadline
+    Freeze {
+        /// The group id to freeze the projects of
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// Access level to lower the students to, see create-individual-repos for the possible values
+        #[arg(short, long, default_value_t = AccessLevel::Reporter.as_u64())]
+        access_level: u64,
+
+        /// File to store the previous access levels in, defaults to freeze-<group_id>.json
+        #[arg(long)]
+        state: Option<PathBuf>,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// Restore the access levels of all students from before a freeze
+    Unfreeze {
+        /// The group id to unfreeze the projects of
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// File the previous access levels were stored in, defaults to freeze-<group_id>.json
+        #[arg(long)]
+        state: Option<PathBuf>,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// Create a protected tag on the last commit before a deadline in all projects within a certain group
+    ///
+    /// The commit is chosen by its committer date, which is set on the student's machine.
+    /// A commit made before but pushed after the deadline is still tagged, see late-report.
+    TagDeadline {
+        /// The group id to tag the projects of
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// The deadline as RFC 3339 timestamp, e.g. 2024-01-19T23:59:59+01:00
+        #[arg(short, long, required = true)]
+        deadline: DateTime<Utc>,
+
+        /// Name of the tag to create
+        #[arg(short, long, default_value = "submission-final")]
+        tag: String,
+
+        /// Branch to take the commit from, defaults to the default branch of every project
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        /// Write a CSV report of all projects to this file
+        #[arg(short, long)]
+        report: Option<PathBuf>,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// List all commits pushed after a deadline in all projects within a certain group
+    ///
+    /// Pushes are taken from the project events, so commits are only listed for as long as gitlab
+    /// keeps those.
+    LateReport {
+        /// The group id to list the late commits of
+        #[arg(required = true)]
+        group_id: u64,
+
+        /// The deadline as RFC 3339 timestamp, e.g. 2024-01-19T23:59:59+01:00
+        #[arg(short, long, required = true)]
+        deadline: DateTime<Utc>,
+
+        /// Branch to list the pushes to, defaults to the default branch of every project
+        #[arg(short, long)]
+        branch: Option<String>,
+
+        #[arg(short, long, value_enum, default_value_t = ReportFormat::Csv)]
+        format: ReportFormat,
+
+        /// File to write the report to, defaults to stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Brightspace Organizational Unit ID, to map individual repositories to students
+        #[arg(long = "ou")]
+        brightspace_ou: Option<u64>,
+
+        /// Prefix of the individual repositories
+        #[arg(short = 'p', long = "prefix")]
+        repo_name_prefix: Option<String>,
+
+        /// Brightspace group category, to map group repositories to their members
+        #[arg(long = "brightspace")]
+        brightspace_group_id: Option<u64>,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
     /// Remove the fork relation from all project within a certain group
     Unfork {
         /// The group id to unfork all repos under
@@ -70,12 +326,19 @@ enum Commands {
     /// Using a brightspace classlist create a repository for every student
     CreateIndividualRepos {
         /// Brightspace Organizational Unit ID to use the classlist from
-        #[arg(long = "ou", required = true)]
-        brightspace_ou: u64,
+        #[arg(long = "ou", required_unless_present = "roster")]
+        brightspace_ou: Option<u64>,
+
+        /// Read the students from a local classlist JSON or CSV file instead of brightspace
+        #[arg(long)]
+        roster: Option<PathBuf>,
 
         #[command(flatten)]
         brightspace: BrightspaceArgs,
 
+        #[command(flatten)]
+        filter: StudentFilterArgs,
+
         #[command(flatten)]
         gitlab: GitlabArgs,
 
@@ -85,13 +348,17 @@ enum Commands {
         /// Prefix to add to all created repositories
         #[arg(short = 'p', long = "prefix")]
         repo_name_prefix: Option<String>,
+
+        /// Create a subgroup per brightspace section and put the repositories of its students in it
+        #[arg(long, default_value_t = false)]
+        by_section: bool,
     },
 
     /// Using the brightspace groups, create corresponding repositories
+    #[command(alias = "create-group-repos")]
     CreateGroupReposBrightspace {
-        /// The category id of the list of brightspace groups, visible in the edit url in brightspace
-        #[arg(short, long = "brightspace", required = true)]
-        brightspace_group_id: u64,
+        #[command(flatten)]
+        groups: BrightspaceGroupArgs,
 
         #[command(flatten)]
         gitlab: GitlabArgs,
@@ -103,7 +370,154 @@ enum Commands {
         project: GitlabProjectCreationArgs,
     },
 
-    /// Retrieve a CSV file containing all students from brightspace
+    /// Compare the individual repositories against the brightspace classlist,
+    /// and handle the repositories of students who are no longer enrolled
+    SyncRoster {
+        /// Brightspace Organizational Unit ID to use the classlist from
+        #[arg(long = "ou", required = true)]
+        brightspace_ou: u64,
+
+        /// Gitlab Group ID containing the individual repositories
+        #[arg(required = true)]
+        gitlab_group_id: u64,
+
+        /// Prefix of the individual repositories, required unless the action is report
+        #[arg(short = 'p', long = "prefix")]
+        repo_name_prefix: Option<String>,
+
+        /// What to do with the repositories of students who are no longer enrolled
+        #[arg(long, value_enum, default_value_t = DroppedAction::Report)]
+        action: DroppedAction,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+    },
+
+    /// Update the members of existing group repositories to match the brightspace groups
+    SyncGroupRepos {
+        #[command(flatten)]
+        groups: BrightspaceGroupArgs,
+
+        /// Gitlab Group ID containing the group repositories
+        #[arg(required = true)]
+        gitlab_group_id: u64,
+
+        /// Access level to give to students who are added to a repository
+        #[arg(short, long, default_value_t = AccessLevel::Developer.as_u64())]
+        access_level: u64,
+
+        /// What to do with members who are no longer in the group
+        #[arg(long, value_enum, default_value_t = RemovedAction::Remove)]
+        removed: RemovedAction,
+
+        /// Access level to downgrade members who are no longer in the group to
+        #[arg(long, default_value_t = AccessLevel::Guest.as_u64())]
+        downgrade_to: u64,
+
+        /// Apply the changes without asking for confirmation
+        #[arg(short, long, default_value_t = false)]
+        yes: bool,
+
+        #[command(flatten)]
+        gitlab: GitlabArgs,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// Retrieve a CSV file containing the grades of all students from brightspace,
+    /// with a column per grade object
+    GradesCsv {
+        /// The course's "ou" or orgUnitId
+        #[arg(required = true)]
+        course_id: u64,
+
+        #[arg(short, long = "file", default_value = "grades.csv")]
+        output_file: PathBuf,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// Write grades from a CSV file (netid,score,feedback) to a brightspace grade object
+    PushGrades {
+        /// Brightspace Organizational Unit ID of the course
+        #[arg(long = "ou", required = true)]
+        brightspace_ou: u64,
+
+        /// Id of the (numeric) grade object to write the grades to
+        #[arg(short, long, required = true)]
+        grade_object: u64,
+
+        /// CSV file with the columns netid (or project), score and feedback
+        #[arg(required = true)]
+        grades_file: PathBuf,
+
+        /// Prefix to strip from project names to get the netid of a student
+        #[arg(short = 'p', long = "prefix")]
+        repo_name_prefix: Option<String>,
+
+        /// Brightspace group category, gives every member of a group the grade of their group
+        #[arg(long)]
+        by_group: Option<u64>,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// List the group categories of a brightspace course
+    GroupCategories {
+        /// The course's "ou" or orgUnitId
+        #[arg(required = true)]
+        course_id: u64,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// List the roles of everyone enrolled in a brightspace course
+    Roles {
+        /// The course's "ou" or orgUnitId
+        #[arg(required = true)]
+        course_id: u64,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// List the dropbox folders of a brightspace course
+    DropboxFolders {
+        /// The course's "ou" or orgUnitId
+        #[arg(required = true)]
+        course_id: u64,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// Download the latest submission of every student in brightspace dropbox folders
+    DropboxDownload {
+        /// The course's "ou" or orgUnitId
+        #[arg(required = true)]
+        course_id: u64,
+
+        /// Ids of the dropbox folders to download, defaults to all folders
+        #[arg(short, long = "folder")]
+        folders: Vec<u64>,
+
+        /// Directory to write the submissions to
+        #[arg(short, long = "out", default_value = "submissions")]
+        output_dir: PathBuf,
+
+        #[command(flatten)]
+        brightspace: BrightspaceArgs,
+    },
+
+    /// Retrieve a CSV file containing all students from brightspace,
+    /// with their sections, role and enrollment date
     ClasslistCsv {
         /// The course's "ou" or orgUnitId
         #[arg(required = true)]
@@ -118,6 +532,9 @@ enum Commands {
 
         #[command(flatten)]
         brightspace: BrightspaceArgs,
+
+        #[command(flatten)]
+        filter: StudentFilterArgs,
     },
 }
 
@@ -138,9 +555,26 @@ struct GitlabArgs {
 
 #[derive(Debug, Args)]
 struct BrightspaceArgs {
+    /// Learning management system to use
+    #[arg(long, value_enum, default_value_t = LmsKind::Brightspace)]
+    lms: LmsKind,
+
     #[arg(long, hide = true, default_value = "https://brightspace.tudelft.nl")]
     base_url: http::Uri,
 
+    /// Url of the canvas instance, e.g. https://canvas.example.edu
+    #[arg(long, required_if_eq("lms", "canvas"))]
+    canvas_url: Option<String>,
+
+    /// Canvas access token
+    #[arg(
+        long,
+        env = "CANVAS_TOKEN",
+        hide_env_values = true,
+        required_if_eq("lms", "canvas")
+    )]
+    canvas_token: Option<String>,
+
     /// Brightspace Cookie
     #[arg(long, env = "BRIGHTSPACE_COOKIE", default_value_t = retrieve_brightspace_cookies(),  hide_env_values = true)]
     cookie: String,
@@ -148,6 +582,199 @@ struct BrightspaceArgs {
     // Brightspace LTI Session ID
     #[arg(long, env = "BRIGHTSPACE_SESSIONID", default_value_t = retrieve_brightspace_cookies(), hide_env_values = true)]
     session_id: String,
+
+    /// How to authenticate against brightspace
+    #[arg(long, value_enum, default_value_t = AuthMode::Auto)]
+    auth: AuthMode,
+
+    /// Client id of the brightspace OAuth2 application
+    #[arg(long, env = "BRIGHTSPACE_CLIENT_ID")]
+    client_id: Option<String>,
+
+    /// Client secret of the brightspace OAuth2 application
+    #[arg(long, env = "BRIGHTSPACE_CLIENT_SECRET", hide_env_values = true)]
+    client_secret: Option<String>,
+
+    /// Redirect uri registered for the OAuth2 application, coursectl listens on it for the authorization code
+    #[arg(long, default_value = "http://localhost:8765/callback")]
+    redirect_uri: String,
+
+    /// OAuth2 scopes to request
+    #[arg(long, default_value = "core:*:*")]
+    scope: String,
+
+    /// File to cache the OAuth2 tokens in
+    #[arg(long, default_value_os_t = oauth::default_token_cache())]
+    token_cache: PathBuf,
+
+    #[command(flatten)]
+    netids: NetidArgs,
+}
+
+#[derive(Debug, Args)]
+struct NetidArgs {
+    /// Domains to strip from brightspace usernames to get the netid
+    #[arg(long = "netid-domain", value_delimiter = ',', default_value = netid::DEFAULT_DOMAIN)]
+    netid_domains: Vec<String>,
+
+    /// Regex extracting the netid from usernames, using the first capture group if there is one
+    #[arg(long)]
+    netid_pattern: Option<String>,
+
+    /// Use the local part of the email address for users whose netid can't be determined otherwise
+    #[arg(long, default_value_t = false)]
+    netid_email_fallback: bool,
+}
+
+impl NetidArgs {
+    fn mapping(&self) -> Result<NetidMapping> {
+        // defaults from coursectl.toml are not split on the delimiter
+        let domains = self
+            .netid_domains
+            .iter()
+            .flat_map(|d| d.split(','))
+            .map(str::to_string)
+            .collect();
+
+        NetidMapping::new(
+            domains,
+            self.netid_pattern.as_deref(),
+            self.netid_email_fallback,
+        )
+    }
+}
+
+impl BrightspaceArgs {
+    /// Creates a brightspace client, negotiating the api versions to use
+    ///
+    /// This talks to brightspace (and may ask to log in), so commands create it only once.
+    fn client(&self) -> Result<brightspace::Client> {
+        let cookie = || brightspace::Auth::Cookie(self.cookie.clone());
+
+        let auth = match (self.auth, &self.client_id) {
+            (AuthMode::Cookie, _) | (AuthMode::Auto, None) => cookie(),
+            (AuthMode::Oauth, None) => bail!("--client-id is required for oauth authentication"),
+            (mode, Some(client_id)) => {
+                let config = oauth::OAuthConfig {
+                    client_id: client_id.clone(),
+                    client_secret: self.client_secret.clone().unwrap_or_default(),
+                    redirect_uri: self.redirect_uri.clone(),
+                    scope: self.scope.clone(),
+                    cache: self.token_cache.clone(),
+                };
+
+                match oauth::OAuthToken::new(config) {
+                    Ok(token) => brightspace::Auth::OAuth(token),
+                    Err(e) if mode == AuthMode::Auto => {
+                        eprintln!("OAuth2 authentication failed, falling back to cookies: {e:#}");
+                        cookie()
+                    }
+                    Err(e) => return Err(e.wrap_err("OAuth2 authentication failed")),
+                }
+            }
+        };
+
+        brightspace::Client::new(self.base_url.clone(), auth, self.netids.mapping()?)
+    }
+
+    /// Creates a client for the selected learning management system, also once per command
+    fn lms(&self) -> Result<Box<dyn Lms>> {
+        Ok(match self.lms {
+            LmsKind::Brightspace => Box::new(self.client()?),
+            LmsKind::Canvas => Box::new(Canvas::new(
+                self.canvas_url
+                    .as_deref()
+                    .wrap_err("--canvas-url is required")?,
+                self.canvas_token
+                    .clone()
+                    .wrap_err("--canvas-token is required")?,
+                self.netids.mapping()?,
+            )),
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+struct StudentFilterArgs {
+    /// Roles to include, by role id or (classlist display) name, defaults to students
+    #[arg(long = "role")]
+    roles: Vec<Role>,
+
+    /// Only include students in these sections
+    #[arg(long = "section")]
+    sections: Vec<String>,
+
+    /// Only include students who enrolled after this moment, e.g. 2024-09-01T00:00:00Z
+    #[arg(long)]
+    enrolled_after: Option<DateTime<Utc>>,
+}
+
+impl StudentFilterArgs {
+    fn filter(&self) -> StudentFilter {
+        StudentFilter {
+            roles: self.roles.clone(),
+            sections: self.sections.clone(),
+            enrolled_after: self.enrolled_after,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct BrightspaceGroupArgs {
+    /// The category id of the list of brightspace groups, visible in the edit url in brightspace
+    #[arg(short, long = "brightspace", required_unless_present = "roster")]
+    brightspace_group_id: Option<u64>,
+
+    /// Brightspace Organizational Unit ID of the course the groups belong to
+    #[arg(long = "ou", required_unless_present_any = ["lti", "roster"])]
+    brightspace_ou: Option<u64>,
+
+    /// Retrieve the groups through the group-impexp LTI tool instead of the brightspace API
+    #[arg(long, default_value_t = false)]
+    lti: bool,
+
+    /// Read the groups from a local CSV file with a group_name, netid and email column instead
+    #[arg(long, conflicts_with = "lti")]
+    roster: Option<PathBuf>,
+}
+
+impl BrightspaceGroupArgs {
+    fn get_groups(&self, brightspace: &BrightspaceArgs) -> Result<Vec<Group>> {
+        if self.roster.is_none() && (self.lti || self.brightspace_ou.is_none()) {
+            let category = self
+                .brightspace_group_id
+                .wrap_err("a brightspace group category is required without --roster")?;
+
+            return brightspace::get_groups_lti(
+                &brightspace.session_id,
+                &category.to_string(),
+                &brightspace.netids.mapping()?,
+            );
+        }
+
+        self.roster(brightspace)?.groups()
+    }
+
+    fn roster(&self, brightspace: &BrightspaceArgs) -> Result<Box<dyn Roster>> {
+        Ok(match &self.roster {
+            Some(path) => Box::new(FileRoster {
+                path: path.clone(),
+                filter: StudentFilter::default(),
+                netids: brightspace.netids.mapping()?,
+            }),
+            None => Box::new(LmsRoster {
+                lms: brightspace.lms()?,
+                course: self
+                    .brightspace_ou
+                    .wrap_err("--ou is required without --roster")?,
+                filter: StudentFilter::default(),
+                group_category: Some(
+                    self.brightspace_group_id
+                        .wrap_err("a brightspace group category is required without --roster")?,
+                ),
+            }),
+        })
+    }
 }
 
 #[derive(Debug, Args)]
@@ -171,6 +798,22 @@ struct GitlabProjectCreationArgs {
     /// Admin => 60,
     #[arg(short, long, default_value_t = AccessLevel::Developer.as_u64())]
     access_level: u64,
+
+    /// Journal recording the progress of every repository, used to resume an interrupted run
+    ///
+    /// Defaults to `coursectl-journal-<gitlab group id>.jsonl` in the current directory.
+    #[arg(long)]
+    journal: Option<PathBuf>,
+}
+
+impl GitlabProjectCreationArgs {
+    fn journal(&self, dry_run: bool) -> Result<Journal> {
+        let path = self.journal.clone().unwrap_or_else(|| {
+            PathBuf::from(format!("coursectl-journal-{}.jsonl", self.gitlab_group_id))
+        });
+
+        Journal::open(&path, dry_run)
+    }
 }
 
 /// inserts `user:token` into http urls to be able to clone private repos
@@ -227,8 +870,25 @@ fn retrieve_brightspace_cookies() -> String {
 fn main() -> Result<()> {
     color_eyre::install()?;
     dotenvy::dotenv().ok();
-    let cli = Cli::parse();
 
+    let config = config::Config::load()?;
+    let mut cmd = Cli::command();
+    if let Some(course) = config.course(config::selected_course(&cmd).as_deref())? {
+        cmd = course.apply(cmd);
+    }
+    let cli = Cli::from_arg_matches(&cmd.get_matches()).unwrap_or_else(|e| e.exit());
+    let mut failures = Failures::new(cli.keep_going, cli.retry_from.as_deref())?;
+
+    let failures_file = cli.failures_file.clone();
+    let res = run(cli, &mut failures);
+
+    netid::report_unmapped();
+    // Also report what failed before an error aborted the operation
+    let finished = failures.finish(failures_file.as_deref());
+    res.and(finished)
+}
+
+fn run(cli: Cli, failures: &mut Failures) -> Result<()> {
     match cli.command {
         Commands::Projects { gitlab, group_id } => {
             let client =
@@ -236,55 +896,367 @@ fn main() -> Result<()> {
 
             projects::list(&client, group_id)?;
         }
+        Commands::CloneAll {
+            group_id,
+            output_dir,
+            branch,
+            gitlab,
+        } => {
+            let client =
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+
+            clone::clone_all(
+                &client,
+                group_id,
+                &output_dir,
+                branch.as_deref(),
+                failures,
+                cli.dry_run,
+            )?;
+        }
+        Commands::PushTemplate {
+            group_id,
+            template_dir,
+            branch,
+            source,
+            merge,
+            gitlab,
+        } => {
+            let client =
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+
+            push_template::push_template(
+                &client,
+                &template_dir,
+                group_id,
+                source.as_deref().unwrap_or(&branch),
+                &branch,
+                merge,
+                failures,
+                cli.dry_run,
+            )?;
+        }
+        Commands::CreateFixMr {
+            group_id,
+            template_dir,
+            source_branch,
+            target_branch,
+            title,
+            description,
+            remove_source_branch,
+            assignee,
+            gitlab,
+        } => {
+            let client =
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+
+            let mr = merge_requests::FixMergeRequest {
+                source_branch: &source_branch,
+                target_branch: &target_branch,
+                title: &title,
+                description: description.as_deref(),
+                remove_source_branch,
+                assignee,
+            };
+
+            merge_requests::create_fix_mrs(
+                &client,
+                &template_dir,
+                group_id,
+                &mr,
+                failures,
+                cli.dry_run,
+            )?;
+        }
+        Commands::Collect {
+            group_id,
+            patterns,
+            ref_,
+            output_dir,
+            gitlab,
+        } => {
+            let client =
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+
+            collect::collect(
+                &client,
+                group_id,
+                &patterns,
+                ref_.as_deref(),
+                &output_dir,
+                failures,
+                cli.dry_run,
+            )?;
+        }
         Commands::Unprotect {
             gitlab,
             group_id,
             branch,
+        } => {
+            let client = RateLimitedClient::new(
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?,
+            );
+
+            projects::unprotect(&client, group_id, &branch, cli.jobs, failures, cli.dry_run)?;
+        }
+        Commands::Freeze {
+            group_id,
+            access_level,
+            state,
+            gitlab,
         } => {
             let client =
                 Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+            let state = state.unwrap_or_else(|| format!("freeze-{group_id}.json").into());
 
-            projects::unprotect(&client, group_id, &branch, cli.dry_run)?;
+            freeze::freeze(
+                &client,
+                group_id,
+                &gitlab.user,
+                u64_to_access_level(access_level),
+                &state,
+                failures,
+                cli.dry_run,
+            )?;
         }
-        Commands::Unfork { group_id, gitlab } => {
+        Commands::Unfreeze {
+            group_id,
+            state,
+            gitlab,
+        } => {
             let client =
                 Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
-            projects::unfork(&client, group_id, cli.dry_run)?;
+            let state = state.unwrap_or_else(|| format!("freeze-{group_id}.json").into());
+
+            freeze::unfreeze(&client, group_id, &state, failures, cli.dry_run)?;
         }
-        Commands::RemoveNonDefaultBranches { group_id, gitlab } => {
+        Commands::TagDeadline {
+            group_id,
+            deadline,
+            tag,
+            branch,
+            report,
+            gitlab,
+        } => {
             let client =
                 Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
-            projects::remove_non_default_branches(&client, group_id, cli.dry_run)?;
+
+            deadline::tag_deadline(
+                &client,
+                group_id,
+                deadline,
+                &tag,
+                branch.as_deref(),
+                report.as_deref(),
+                failures,
+                cli.dry_run,
+            )?;
+        }
+        Commands::LateReport {
+            group_id,
+            deadline,
+            branch,
+            format,
+            output,
+            brightspace_ou,
+            repo_name_prefix,
+            brightspace_group_id,
+            brightspace,
+            gitlab,
+        } => {
+            let client =
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+
+            let lms = match brightspace_ou {
+                Some(_) => Some(brightspace.lms()?),
+                None => None,
+            };
+
+            let mut owners = HashMap::new();
+            if let (Some(ou), Some(lms)) = (brightspace_ou, &lms) {
+                let students = lms.students(ou, &StudentFilter::default())?;
+                owners.extend(students.into_iter().map(|s| {
+                    (
+                        create_repos::repo_name(&repo_name_prefix, &s.student.netid),
+                        vec![s.student.netid],
+                    )
+                }));
+            }
+            if let Some(category) = brightspace_group_id {
+                let groups = match (brightspace_ou, &lms) {
+                    (Some(ou), Some(lms)) => lms.groups(ou, category)?,
+                    _ => brightspace::get_groups_lti(
+                        &brightspace.session_id,
+                        &category.to_string(),
+                        &brightspace.netids.mapping()?,
+                    )?,
+                };
+                owners.extend(
+                    groups
+                        .into_iter()
+                        .map(|g| (g.name, g.members.into_iter().map(|s| s.netid).collect())),
+                );
+            }
+
+            deadline::late_report(
+                &client,
+                group_id,
+                deadline,
+                branch.as_deref(),
+                &owners,
+                format,
+                output.as_deref(),
+                failures,
+            )?;
+        }
+        Commands::Unfork { group_id, gitlab } => {
+            let client = RateLimitedClient::new(
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?,
+            );
+            projects::unfork(&client, group_id, cli.jobs, failures, cli.dry_run)?;
+        }
+        Commands::RemoveNonDefaultBranches { group_id, gitlab } => {
+            let client = RateLimitedClient::new(
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?,
+            );
+            projects::remove_non_default_branches(
+                &client,
+                group_id,
+                cli.jobs,
+                failures,
+                cli.dry_run,
+            )?;
         }
         Commands::ClasslistCsv {
             course_id,
             output_file,
             brightspace,
             gitbull,
+            filter,
         } => {
             let f = File::create(output_file).wrap_err("could not create output file")?;
             let mut wtr = csv::Writer::from_writer(f);
 
-            let out =
-                brightspace::get_students(&brightspace.base_url, &brightspace.cookie, course_id)?;
+            let out = brightspace.lms()?.students(course_id, &filter.filter())?;
 
             if gitbull {
-                out.iter()
-                    .try_for_each(|el| wtr.write_record([&el.netid, &el.email, &el.netid]))?;
+                out.iter().try_for_each(|el| {
+                    wtr.write_record([&el.student.netid, &el.student.email, &el.student.netid])
+                })?;
             } else {
-                out.iter().try_for_each(|el| wtr.serialize(el))?;
+                out.into_iter()
+                    .try_for_each(|el| wtr.serialize(StudentRecord::from(el)))?;
             }
             wtr.flush()?;
         }
+        Commands::GroupCategories {
+            course_id,
+            brightspace,
+        } => {
+            let categories = brightspace.lms()?.group_categories(course_id)?;
+
+            for category in categories {
+                println!(
+                    "{} {} ({} groups)",
+                    category.id, category.name, category.groups
+                );
+            }
+        }
+        Commands::Roles {
+            course_id,
+            brightspace,
+        } => {
+            let roles = brightspace.client()?.get_roles(course_id)?;
+
+            for role in roles {
+                println!(
+                    "{} {} [{}] ({} users)",
+                    role.id,
+                    role.name,
+                    role.display_name.as_deref().unwrap_or("-"),
+                    role.users
+                );
+            }
+        }
+        Commands::DropboxFolders {
+            course_id,
+            brightspace,
+        } => {
+            let folders = brightspace.client()?.get_dropbox_folders(course_id)?;
+
+            for folder in folders {
+                println!("{} {}", folder.id, folder.name);
+            }
+        }
+        Commands::DropboxDownload {
+            course_id,
+            folders,
+            output_dir,
+            brightspace,
+        } => {
+            dropbox::download_submissions(
+                &brightspace.client()?,
+                course_id,
+                &folders,
+                &output_dir,
+                failures,
+                cli.dry_run,
+            )?;
+        }
+        Commands::GradesCsv {
+            course_id,
+            output_file,
+            brightspace,
+        } => {
+            let f = File::create(output_file).wrap_err("could not create output file")?;
+
+            grades::write_grades_csv(brightspace.lms()?.as_ref(), course_id, f)?;
+        }
+        Commands::PushGrades {
+            brightspace_ou,
+            grade_object,
+            grades_file,
+            repo_name_prefix,
+            by_group,
+            brightspace,
+        } => {
+            let records: Vec<GradeRecord> = csv::Reader::from_path(grades_file)
+                .wrap_err("could not open grades file")?
+                .deserialize()
+                .collect::<Result<_, _>>()
+                .wrap_err("failed parsing grades file")?;
+
+            let lms = brightspace.lms()?;
+
+            let groups = match by_group {
+                Some(category) => lms.groups(brightspace_ou, category)?,
+                None => Vec::new(),
+            };
+
+            let grades = grades::expand_grade_records(records, &repo_name_prefix, &groups);
+
+            grades::push_grades(
+                lms.as_ref(),
+                brightspace_ou,
+                grade_object,
+                &grades,
+                failures,
+                cli.dry_run,
+            )?;
+        }
         Commands::CreateIndividualRepos {
             gitlab,
             project,
             repo_name_prefix,
             brightspace,
             brightspace_ou,
+            roster,
+            filter,
+            by_section,
         } => {
-            let client =
-                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
+            let client = RateLimitedClient::new(
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?,
+            );
 
             let template = authenticate_template_repo_url(
                 project.template_repository,
@@ -293,28 +1265,105 @@ fn main() -> Result<()> {
                 &gitlab.token,
             )?;
 
+            let journal = project.journal(cli.dry_run)?;
+
+            let filter = filter.filter();
+            let roster: Box<dyn Roster> = match roster {
+                Some(path) => Box::new(FileRoster {
+                    path,
+                    filter,
+                    netids: brightspace.netids.mapping()?,
+                }),
+                None => Box::new(LmsRoster {
+                    lms: brightspace.lms()?,
+                    course: brightspace_ou.wrap_err("--ou is required without --roster")?,
+                    filter,
+                    group_category: None,
+                }),
+            };
+
             create_repos::create_individual_repos(
                 &client,
                 &repo_name_prefix,
                 project.gitlab_group_id,
                 &template,
                 u64_to_access_level(project.access_level),
-                brightspace,
-                brightspace_ou,
+                roster.as_ref(),
+                by_section,
+                &journal,
+                cli.jobs,
+                failures,
                 cli.dry_run,
             )?;
         }
-        Commands::CreateGroupReposBrightspace {
-            brightspace_group_id,
-            gitlab,
+        Commands::SyncRoster {
+            brightspace_ou,
+            gitlab_group_id,
+            repo_name_prefix,
+            action,
             brightspace,
-            project,
+            gitlab,
         } => {
-            let session_id = brightspace.session_id;
+            let client =
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
 
+            let students: Vec<Student> = brightspace
+                .lms()?
+                .students(brightspace_ou, &StudentFilter::default())
+                .wrap_err("failed getting list of students")?
+                .into_iter()
+                .map(|s| s.student)
+                .collect();
+
+            sync_roster::sync_roster(
+                &client,
+                &students,
+                &repo_name_prefix,
+                gitlab_group_id,
+                &gitlab.user,
+                action,
+                failures,
+                cli.dry_run,
+            )?;
+        }
+        Commands::SyncGroupRepos {
+            groups,
+            gitlab_group_id,
+            access_level,
+            removed,
+            downgrade_to,
+            yes,
+            gitlab,
+            brightspace,
+        } => {
             let client =
                 Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?;
 
+            let groups = groups.get_groups(&brightspace)?;
+
+            sync_groups::sync_group_members(
+                &client,
+                gitlab_group_id,
+                &groups,
+                &gitlab.user,
+                u64_to_access_level(access_level),
+                removed,
+                u64_to_access_level(downgrade_to),
+                failures,
+                cli.dry_run,
+                yes,
+            )?;
+        }
+        Commands::CreateGroupReposBrightspace {
+            groups,
+            gitlab,
+            brightspace,
+            project,
+        } => {
+            let client = RateLimitedClient::new(
+                Gitlab::new(&gitlab.host, &gitlab.token).wrap_err("failed to create git client")?,
+            );
+
             let template = authenticate_template_repo_url(
                 project.template_repository,
                 &gitlab.host,
@@ -322,7 +1371,8 @@ fn main() -> Result<()> {
                 &gitlab.token,
             )?;
 
-            let groups = brightspace::get_groups(&session_id, &brightspace_group_id.to_string())?;
+            let groups = groups.get_groups(&brightspace)?;
+            let journal = project.journal(cli.dry_run)?;
 
             create_repos::create_group_repos(
                 &client,
@@ -330,6 +1380,9 @@ fn main() -> Result<()> {
                 &template,
                 u64_to_access_level(project.access_level),
                 &groups,
+                &journal,
+                cli.jobs,
+                failures,
                 cli.dry_run,
             )?;
         }
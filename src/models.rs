@@ -1,8 +1,10 @@
 use std::collections::HashMap;
 
-use color_eyre::{eyre::ContextCompat, Report, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::netid::NetidMapping;
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Student {
     pub netid: String,
@@ -18,6 +20,17 @@ pub struct StudentGroupEntry {
     pub student_number: Option<u64>,
 }
 
+/// A row of a grades CSV file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradeRecord {
+    /// netid of the student, or the name of their project
+    #[serde(alias = "project", alias = "group")]
+    pub netid: String,
+    pub score: f64,
+    #[serde(default)]
+    pub feedback: Option<String>,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub struct Group {
     pub name: String,
@@ -43,6 +56,85 @@ pub struct ProjectInfo {
     pub id: u64,
     pub name: String,
     pub ssh_url_to_repo: String,
+    /// Empty repositories don't have a default branch
+    pub default_branch: Option<String>,
+}
+
+impl ProjectInfo {
+    /// Name of the project usable as a git remote or directory name
+    pub fn remote_name(&self) -> String {
+        self.name.replace(' ', "-").to_lowercase()
+    }
+}
+
+/// See <https://docs.gitlab.com/ee/api/groups.html#details-of-a-group>
+#[derive(Debug, Clone, Deserialize)]
+pub struct GroupInfo {
+    pub id: u64,
+    pub name: String,
+    pub path: String,
+}
+
+/// See <https://docs.gitlab.com/ee/api/members.html#list-all-members-of-a-group-or-project>
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectMember {
+    pub id: u64,
+    pub username: String,
+    pub access_level: u64,
+}
+
+/// See <https://docs.gitlab.com/ee/api/invitations.html#list-all-invitations-pending-for-a-group-or-project>
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProjectInvitation {
+    pub invite_email: String,
+    pub access_level: u64,
+}
+
+/// See <https://docs.gitlab.com/ee/api/commits.html#list-repository-commits>
+#[derive(Debug, Clone, Deserialize)]
+pub struct Commit {
+    pub id: String,
+    pub short_id: String,
+    pub title: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub authored_date: DateTime<Utc>,
+    pub committed_date: DateTime<Utc>,
+}
+
+/// See <https://docs.gitlab.com/ee/api/repositories.html#compare-branches-tags-or-commits>
+#[derive(Debug, Clone, Deserialize)]
+pub struct Comparison {
+    /// Oldest first
+    pub commits: Vec<Commit>,
+}
+
+/// See <https://docs.gitlab.com/ee/api/events.html#list-a-projects-visible-events>
+#[derive(Debug, Clone, Deserialize)]
+pub struct PushEvent {
+    pub author_username: String,
+    pub created_at: DateTime<Utc>,
+    pub push_data: PushData,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PushData {
+    pub commit_count: u64,
+    pub ref_type: String,
+    #[serde(rename = "ref")]
+    pub ref_: Option<String>,
+    /// Missing when the branch was created
+    pub commit_from: Option<String>,
+    /// Missing when the branch was deleted
+    pub commit_to: Option<String>,
+    pub commit_title: Option<String>,
+}
+
+/// See <https://docs.gitlab.com/ee/api/tags.html#list-project-repository-tags>
+#[derive(Debug, Clone, Deserialize)]
+pub struct Tag {
+    pub name: String,
+    pub commit: Commit,
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,29 +164,152 @@ pub struct BrightspaceClassListEntry {
     pub classlist_role_display_name: String,
 }
 
-impl TryInto<Student> for BrightspaceClassListEntry {
-    type Error = Report;
-
-    fn try_into(self) -> Result<Student> {
-        Ok(Student {
-            email: self.email.wrap_err("student missing email")?,
-            student_number: self
-                .org_defined_id
-                .as_ref()
-                .wrap_err("student missing student nr.")?
-                .parse()
-                .ok(),
-
-            netid: self
-                .username
-                .wrap_err("student missing netid")?
-                .strip_suffix("@tudelft.nl")
-                .wrap_err("failed to strip @tudelft.nl from username")?
-                .to_string(),
+impl BrightspaceClassListEntry {
+    /// Converts the entry to a student, `None` if it has no email or its netid can't be determined
+    pub fn to_student(&self, netids: &NetidMapping) -> Option<Student> {
+        Some(Student {
+            netid: netids.netid(self.username.as_deref(), self.email.as_deref())?,
+            student_number: self.org_defined_id.as_ref().and_then(|id| id.parse().ok()),
+            email: self.email.clone()?,
         })
     }
 }
 
+/// See <https://docs.valence.desire2learn.com/res/grade.html#Grade.GradeObject>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceGradeObject {
+    pub id: u64,
+    pub name: String,
+    pub grade_type: String,
+    pub max_points: Option<f64>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/grade.html#Grade.GradeValue>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceGradeValue {
+    pub displayed_grade: String,
+    pub grade_object_identifier: String,
+    pub grade_object_name: String,
+    pub points_numerator: Option<f64>,
+    pub points_denominator: Option<f64>,
+    pub comments: Option<BrightspaceRichText>,
+    pub private_comments: Option<BrightspaceRichText>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/grade.html#Grade.IncomingGradeValue>
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceIncomingGradeValue {
+    pub comments: BrightspaceRichTextInput,
+    pub private_comments: BrightspaceRichTextInput,
+    pub grade_object_type: u64,
+    pub points_numerator: f64,
+}
+
+/// See <https://docs.valence.desire2learn.com/basic/conventions.html#term-RichText>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceRichText {
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// See <https://docs.valence.desire2learn.com/basic/conventions.html#term-RichTextInput>
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceRichTextInput {
+    pub content: String,
+    #[serde(rename = "Type")]
+    pub kind: &'static str,
+}
+
+impl BrightspaceRichTextInput {
+    pub fn text(content: impl Into<String>) -> Self {
+        Self {
+            content: content.into(),
+            kind: "Text",
+        }
+    }
+}
+
+impl From<&BrightspaceRichText> for BrightspaceRichTextInput {
+    fn from(rich_text: &BrightspaceRichText) -> Self {
+        match &rich_text.html {
+            Some(html) if !html.is_empty() => Self {
+                content: html.clone(),
+                kind: "Html",
+            },
+            _ => Self::text(&rich_text.text),
+        }
+    }
+}
+
+/// See <https://docs.valence.desire2learn.com/res/groups.html#Group.GroupCategoryData>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceGroupCategory {
+    pub group_category_id: u64,
+    pub name: String,
+    pub groups: Vec<u64>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/groups.html#Group.GroupData>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceGroupData {
+    pub group_id: u64,
+    pub name: String,
+    pub code: String,
+    pub enrollments: Vec<u64>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/dropbox.html#Dropbox.DropboxFolder>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceDropboxFolder {
+    pub id: u64,
+    pub name: String,
+    pub total_files: Option<u64>,
+    pub due_date: Option<DateTime<Utc>>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/dropbox.html#Dropbox.EntityDropbox>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceEntityDropbox {
+    pub entity: BrightspaceDropboxEntity,
+    pub submissions: Vec<BrightspaceDropboxSubmission>,
+}
+
+/// The user or group a dropbox submission belongs to
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceDropboxEntity {
+    pub display_name: String,
+    pub entity_id: u64,
+    pub entity_type: String,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/dropbox.html#Dropbox.Submission>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceDropboxSubmission {
+    pub id: u64,
+    pub submission_date: Option<DateTime<Utc>>,
+    pub files: Vec<BrightspaceDropboxFile>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/dropbox.html#Dropbox.File>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceDropboxFile {
+    pub file_id: u64,
+    pub file_name: String,
+    pub size: u64,
+}
+
 /// See: <https://docs.valence.desire2learn.com/res/apiprop.html#Version.ProductVersions>
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
@@ -104,6 +319,96 @@ pub struct BrightspaceProductVersions {
     pub supported_versions: Vec<String>,
 }
 
+/// See <https://docs.valence.desire2learn.com/basic/apicall.html#Api.PagedResultSet>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspacePagedResultSet<T> {
+    pub paging_info: BrightspacePagingInfo,
+    pub items: Vec<T>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspacePagingInfo {
+    pub bookmark: Option<String>,
+    pub has_more_items: bool,
+}
+
+/// See <https://docs.valence.desire2learn.com/basic/apicall.html#Api.ObjectListPage>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceObjectListPage<T> {
+    /// Url of the next page
+    pub next: Option<String>,
+    pub objects: Vec<T>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/enroll.html#Enrollment.OrgUnitUser>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceOrgUnitUser {
+    pub user: BrightspaceUser,
+    pub role: BrightspaceRoleInfo,
+    /// Not returned by every brightspace version
+    #[serde(default)]
+    pub enrollment_date: Option<DateTime<Utc>>,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/user.html#User.User>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceUser {
+    pub identifier: String,
+    pub display_name: Option<String>,
+    pub email_address: Option<String>,
+    pub org_defined_id: Option<String>,
+}
+
+impl BrightspaceUser {
+    /// Converts the user to a student, `None` if they have no email or their netid can't be determined
+    ///
+    /// Users have no username here, it is taken from the classlist instead.
+    pub fn to_student(&self, username: Option<&str>, netids: &NetidMapping) -> Option<Student> {
+        Some(Student {
+            netid: netids.netid(username, self.email_address.as_deref())?,
+            student_number: self.org_defined_id.as_ref().and_then(|id| id.parse().ok()),
+            email: self.email_address.clone()?,
+        })
+    }
+}
+
+/// See <https://docs.valence.desire2learn.com/res/enroll.html#Enrollment.RoleInfo>
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceRoleInfo {
+    pub id: u64,
+    pub code: Option<String>,
+    pub name: String,
+}
+
+/// See <https://docs.valence.desire2learn.com/res/enroll.html#Section.SectionData>
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct BrightspaceSection {
+    pub section_id: u64,
+    pub name: String,
+    pub code: String,
+    pub enrollments: Vec<u64>,
+}
+
+/// A user enrolled in a course, with their role and sections
+#[derive(Debug, Clone)]
+pub struct Enrollment {
+    /// Identifier of the user in the LMS
+    pub identifier: String,
+    pub display_name: String,
+    pub role_id: u64,
+    pub role_name: String,
+    /// Names of the sections the user is enrolled in
+    pub sections: Vec<String>,
+    pub enrollment_date: Option<DateTime<Utc>>,
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "PascalCase")]
 pub struct BrightspaceGroupRecord {
@@ -116,18 +421,84 @@ pub struct BrightspaceGroupRecord {
     pub email: String,
 }
 
-impl TryInto<Student> for BrightspaceGroupRecord {
-    type Error = Report;
-
-    fn try_into(self) -> std::result::Result<Student, Self::Error> {
-        Ok(Student {
-            netid: self
-                .username
-                .strip_suffix("@tudelft.nl")
-                .wrap_err("failed to strip error")?
-                .to_string(),
+impl BrightspaceGroupRecord {
+    /// Converts the record to a student, `None` if its netid can't be determined
+    pub fn to_student(&self, netids: &NetidMapping) -> Option<Student> {
+        Some(Student {
+            netid: netids.netid(Some(&self.username), Some(&self.email))?,
             student_number: self.org_defined_id,
-            email: self.email,
+            email: self.email.clone(),
         })
     }
 }
+
+/// See <https://canvas.instructure.com/doc/api/users.html#User>
+#[derive(Debug, Deserialize)]
+pub struct CanvasUser {
+    pub id: u64,
+    pub name: String,
+    pub login_id: Option<String>,
+    pub sis_user_id: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub enrollments: Vec<CanvasEnrollment>,
+}
+
+/// See <https://canvas.instructure.com/doc/api/enrollments.html#Enrollment>
+#[derive(Debug, Deserialize)]
+pub struct CanvasEnrollment {
+    pub course_section_id: Option<u64>,
+    /// e.g. `StudentEnrollment` or `TaEnrollment`
+    #[serde(rename = "type")]
+    pub enrollment_type: String,
+    pub role: String,
+    pub role_id: u64,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// See <https://canvas.instructure.com/doc/api/sections.html#Section>
+#[derive(Debug, Deserialize)]
+pub struct CanvasSection {
+    pub id: u64,
+    pub name: String,
+}
+
+/// See <https://canvas.instructure.com/doc/api/group_categories.html#GroupCategory>
+#[derive(Debug, Deserialize)]
+pub struct CanvasGroupCategory {
+    pub id: u64,
+    pub name: String,
+}
+
+/// See <https://canvas.instructure.com/doc/api/groups.html#Group>
+#[derive(Debug, Deserialize)]
+pub struct CanvasGroup {
+    pub id: u64,
+    pub name: String,
+}
+
+/// See <https://canvas.instructure.com/doc/api/assignments.html#Assignment>
+#[derive(Debug, Deserialize)]
+pub struct CanvasAssignment {
+    pub id: u64,
+    pub name: String,
+    pub points_possible: Option<f64>,
+}
+
+/// See <https://canvas.instructure.com/doc/api/submissions.html#Submission>
+#[derive(Debug, Deserialize)]
+pub struct CanvasSubmission {
+    pub assignment_id: u64,
+    pub user_id: u64,
+    pub score: Option<f64>,
+    pub grade: Option<String>,
+    /// Only returned with `include[]=submission_comments`
+    #[serde(default)]
+    pub submission_comments: Vec<CanvasSubmissionComment>,
+}
+
+/// See <https://canvas.instructure.com/doc/api/submissions.html#SubmissionComment>
+#[derive(Debug, Deserialize)]
+pub struct CanvasSubmissionComment {
+    pub comment: String,
+}
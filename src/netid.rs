@@ -0,0 +1,156 @@
+use std::sync::Mutex;
+
+use color_eyre::eyre::{Context, Result};
+use regex::Regex;
+
+/// Domain of the usernames of TU Delft accounts
+pub const DEFAULT_DOMAIN: &str = "tudelft.nl";
+
+/// Determines the netid of a user from their brightspace username or email address
+///
+/// The rules are tried in order:
+/// 1. the first capture group (or the whole match) of `pattern` on the username
+/// 2. the username without `@domain`, for any of the `domains`
+/// 3. the username itself, if it has no domain and `bare_usernames` is enabled
+/// 4. the local part of the email address, if `email_fallback` is enabled
+#[derive(Debug, Clone)]
+pub struct NetidMapping {
+    pub domains: Vec<String>,
+    pub pattern: Option<Regex>,
+    pub email_fallback: bool,
+    /// Whether usernames are netids without a domain, like the login ids of canvas
+    pub bare_usernames: bool,
+}
+
+impl Default for NetidMapping {
+    fn default() -> Self {
+        Self {
+            domains: vec![DEFAULT_DOMAIN.to_string()],
+            pattern: None,
+            email_fallback: false,
+            bare_usernames: false,
+        }
+    }
+}
+
+impl NetidMapping {
+    pub fn new(domains: Vec<String>, pattern: Option<&str>, email_fallback: bool) -> Result<Self> {
+        let pattern = pattern
+            .map(Regex::new)
+            .transpose()
+            .wrap_err("invalid netid pattern")?;
+
+        Ok(Self {
+            domains,
+            pattern,
+            email_fallback,
+            bare_usernames: false,
+        })
+    }
+
+    /// Returns the netid of a user, or `None` if none of the rules apply
+    pub fn netid(&self, username: Option<&str>, email: Option<&str>) -> Option<String> {
+        if let Some(username) = username {
+            if let Some(captures) = self.pattern.as_ref().and_then(|p| p.captures(username)) {
+                let netid = captures.get(1).or_else(|| captures.get(0))?;
+                return Some(netid.as_str().to_string());
+            }
+
+            match username.rsplit_once('@') {
+                Some((local, domain)) => {
+                    if self.domains.iter().any(|d| d.eq_ignore_ascii_case(domain)) {
+                        return Some(local.to_string());
+                    }
+                }
+                None if self.bare_usernames && !username.is_empty() => {
+                    return Some(username.to_string())
+                }
+                None => {}
+            }
+        }
+
+        if self.email_fallback {
+            let (local, _) = email?.split_once('@')?;
+            return Some(local.to_lowercase());
+        }
+
+        None
+    }
+}
+
+/// Users who were skipped during this run, see [`report_unmapped`]
+static UNMAPPED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Records users who were skipped because their netid could not be determined
+///
+/// They are printed by [`report_unmapped`] once the command finished, a list printed while the
+/// roster is retrieved would scroll away before a long operation even started.
+pub fn skip_unmapped(users: Vec<String>) {
+    let mut unmapped = UNMAPPED.lock().unwrap();
+    for user in users {
+        if !unmapped.contains(&user) {
+            unmapped.push(user);
+        }
+    }
+}
+
+/// Prints the users who were skipped because their netid could not be determined
+pub fn report_unmapped() {
+    let unmapped = UNMAPPED.lock().unwrap();
+    if unmapped.is_empty() {
+        return;
+    }
+
+    eprintln!(
+        "Skipped {} users whose netid could not be determined:",
+        unmapped.len()
+    );
+    for user in unmapped.iter() {
+        eprintln!("  {user}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NetidMapping;
+
+    #[test]
+    fn maps_usernames() {
+        let default = NetidMapping::default();
+        assert_eq!(
+            default.netid(Some("jdoe@tudelft.nl"), None).as_deref(),
+            Some("jdoe")
+        );
+        assert_eq!(default.netid(Some("guest@example.com"), None), None);
+        assert_eq!(default.netid(Some("jdoe"), None), None);
+
+        let bare = NetidMapping {
+            bare_usernames: true,
+            ..Default::default()
+        };
+        assert_eq!(bare.netid(Some("jdoe"), None).as_deref(), Some("jdoe"));
+
+        let mapping = NetidMapping::new(
+            vec!["tudelft.nl".to_string(), "student.tudelft.nl".to_string()],
+            Some(r"^ext-(\w+)@"),
+            true,
+        )
+        .unwrap();
+        assert_eq!(
+            mapping
+                .netid(Some("jdoe@student.tudelft.nl"), None)
+                .as_deref(),
+            Some("jdoe")
+        );
+        assert_eq!(
+            mapping.netid(Some("ext-jane@uni.edu"), None).as_deref(),
+            Some("jane")
+        );
+        assert_eq!(
+            mapping
+                .netid(Some("guest@example.com"), Some("G.Uest@example.com"))
+                .as_deref(),
+            Some("g.uest")
+        );
+    }
+}
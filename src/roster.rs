@@ -0,0 +1,353 @@
+//! Sources of the students and groups of a course, either a learning management system or a local file
+
+use std::{collections::HashMap, fs::File, path::PathBuf};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{bail, eyre, Context, Result};
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+
+use crate::brightspace::enrollments::STUDENT_ROLE_ID;
+use crate::lms::{Lms, LmsStudent, Role, StudentFilter};
+use crate::models::{BrightspaceClassList, Group, Student};
+use crate::netid::{self, NetidMapping};
+
+/// A student on a roster, with the names of the sections they are in
+#[derive(Debug, Clone)]
+pub struct RosterStudent {
+    pub student: Student,
+    pub sections: Vec<String>,
+    /// Name of the role the student is enrolled with, if the roster has roles
+    pub role: Option<String>,
+    pub enrollment_date: Option<DateTime<Utc>>,
+}
+
+pub trait Roster {
+    fn students(&self) -> Result<Vec<RosterStudent>>;
+
+    fn groups(&self) -> Result<Vec<Group>>;
+}
+
+/// The live roster of a course in a learning management system
+pub struct LmsRoster {
+    pub lms: Box<dyn Lms>,
+    pub course: u64,
+    pub filter: StudentFilter,
+    pub group_category: Option<u64>,
+}
+
+impl Roster for LmsRoster {
+    fn students(&self) -> Result<Vec<RosterStudent>> {
+        Ok(self
+            .lms
+            .students(self.course, &self.filter)?
+            .into_iter()
+            .map(|s| RosterStudent {
+                student: s.student,
+                sections: s.sections,
+                role: Some(s.role),
+                enrollment_date: s.enrollment_date,
+            })
+            .collect())
+    }
+
+    fn groups(&self) -> Result<Vec<Group>> {
+        let category = self
+            .group_category
+            .ok_or_else(|| eyre!("no group category given"))?;
+
+        self.lms.groups(self.course, category)
+    }
+}
+
+/// A roster read from a local file, the format is determined from its contents:
+///
+/// * `.json`: a classlist as returned by brightspace, see `classlist_example.json`
+/// * `.csv` with a `group_name` column: a row per group member with their `netid` and `email`
+/// * any other `.csv`: a row per student as written by `classlist-csv`, optionally with
+///   `section` (separated by `;`), `role` and `enrollment_date` columns
+///
+/// The filter is applied to the students as far as the file allows, selecting sections, roles or
+/// enrollment dates which are missing from the file is an error.
+pub struct FileRoster {
+    pub path: PathBuf,
+    pub filter: StudentFilter,
+    pub netids: NetidMapping,
+}
+
+/// Row of a roster with a student per row, as written by `classlist-csv`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StudentRecord {
+    netid: String,
+    #[serde(default)]
+    student_number: Option<u64>,
+    email: String,
+    #[serde(default)]
+    section: Option<String>,
+    #[serde(default)]
+    role: Option<String>,
+    #[serde(default)]
+    enrollment_date: Option<DateTime<Utc>>,
+}
+
+impl From<LmsStudent> for StudentRecord {
+    fn from(s: LmsStudent) -> Self {
+        Self {
+            netid: s.student.netid,
+            student_number: s.student.student_number,
+            email: s.student.email,
+            section: (!s.sections.is_empty()).then(|| s.sections.join(";")),
+            role: Some(s.role),
+            enrollment_date: s.enrollment_date,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GroupMemberRecord {
+    #[serde(alias = "group")]
+    group_name: String,
+    netid: String,
+    #[serde(default)]
+    student_number: Option<u64>,
+    email: String,
+}
+
+impl FileRoster {
+    fn is_json(&self) -> bool {
+        self.path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    }
+
+    fn csv_reader(&self) -> Result<csv::Reader<File>> {
+        csv::Reader::from_path(&self.path)
+            .wrap_err_with(|| format!("failed reading roster {}", self.path.display()))
+    }
+
+    fn is_group_csv(&self) -> Result<bool> {
+        Ok(self
+            .csv_reader()?
+            .headers()?
+            .iter()
+            .any(|h| h == "group_name" || h == "group"))
+    }
+
+    fn read_classlist(&self) -> Result<Vec<RosterStudent>> {
+        let file = File::open(&self.path)
+            .wrap_err_with(|| format!("failed reading roster {}", self.path.display()))?;
+        let classlist: BrightspaceClassList =
+            serde_json::from_reader(file).wrap_err("failed parsing classlist")?;
+
+        if !self.filter.sections.is_empty() || self.filter.enrolled_after.is_some() {
+            bail!(
+                "classlist {} has no sections or enrollment dates to filter on",
+                self.path.display()
+            );
+        }
+        let roles = self.filter.or_roles(vec![Role::Id(STUDENT_ROLE_ID)]).roles;
+
+        let mut students = Vec::new();
+        let mut unmapped = Vec::new();
+        for entry in classlist {
+            let role = entry.role_id.unwrap_or_default();
+            if !roles
+                .iter()
+                .any(|r| r.matches(role, &[&entry.classlist_role_display_name]))
+            {
+                continue;
+            }
+
+            match entry.to_student(&self.netids) {
+                Some(student) => students.push(RosterStudent {
+                    student,
+                    sections: Vec::new(),
+                    role: Some(entry.classlist_role_display_name),
+                    enrollment_date: None,
+                }),
+                None => unmapped.push(entry.display_name),
+            }
+        }
+
+        netid::skip_unmapped(unmapped);
+        Ok(students)
+    }
+
+    fn read_groups(&self) -> Result<Vec<Group>> {
+        let mut hm: HashMap<String, Vec<Student>> = HashMap::new();
+
+        for row in self.csv_reader()?.deserialize() {
+            let record: GroupMemberRecord = row?;
+            hm.entry(record.group_name.replace(' ', ""))
+                .or_default()
+                .push(Student {
+                    netid: record.netid,
+                    student_number: record.student_number,
+                    email: record.email,
+                });
+        }
+
+        Ok(Group::from_hm(hm))
+    }
+
+    /// Applies the filter to the students of a csv roster
+    fn apply_filter(&self, students: Vec<RosterStudent>) -> Result<Vec<RosterStudent>> {
+        let filter = &self.filter;
+        let path = self.path.display();
+
+        if !filter.sections.is_empty() && students.iter().all(|s| s.sections.is_empty()) {
+            bail!("roster {path} has no sections to filter on");
+        }
+        if filter.enrolled_after.is_some() && students.iter().any(|s| s.enrollment_date.is_none()) {
+            bail!("roster {path} has no enrollment dates to filter on");
+        }
+        if !filter.roles.is_empty() {
+            if students.iter().any(|s| s.role.is_none()) {
+                bail!("roster {path} has no roles to filter on");
+            }
+            if filter.roles.iter().any(|r| matches!(r, Role::Id(_))) {
+                bail!("roster {path} has no role ids, select roles by name instead");
+            }
+        }
+
+        Ok(students
+            .into_iter()
+            .filter(|s| {
+                (filter.sections.is_empty()
+                    || s.sections.iter().any(|x| filter.sections.contains(x)))
+                    && filter.enrolled_after.map_or(true, |after| {
+                        s.enrollment_date.is_some_and(|date| date > after)
+                    })
+                    && (filter.roles.is_empty()
+                        || filter
+                            .roles
+                            .iter()
+                            .any(|r| r.matches(0, &[s.role.as_deref().unwrap_or_default()])))
+            })
+            .collect())
+    }
+}
+
+impl Roster for FileRoster {
+    fn students(&self) -> Result<Vec<RosterStudent>> {
+        if self.is_json() {
+            return self.read_classlist();
+        }
+
+        if self.is_group_csv()? {
+            let students: Vec<RosterStudent> = self
+                .read_groups()?
+                .into_iter()
+                .flat_map(|g| g.members)
+                .unique_by(|s| s.netid.clone())
+                .map(|student| RosterStudent {
+                    student,
+                    sections: Vec::new(),
+                    role: None,
+                    enrollment_date: None,
+                })
+                .collect();
+            return self.apply_filter(students);
+        }
+
+        let students: Vec<RosterStudent> = self
+            .csv_reader()?
+            .deserialize()
+            .map(|row| {
+                let record: StudentRecord = row?;
+                Ok(RosterStudent {
+                    student: Student {
+                        netid: record.netid,
+                        student_number: record.student_number,
+                        email: record.email,
+                    },
+                    sections: record
+                        .section
+                        .iter()
+                        .flat_map(|s| s.split(';'))
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect(),
+                    role: record.role,
+                    enrollment_date: record.enrollment_date,
+                })
+            })
+            .collect::<Result<_>>()?;
+        self.apply_filter(students)
+    }
+
+    fn groups(&self) -> Result<Vec<Group>> {
+        if self.is_json() || !self.is_group_csv()? {
+            bail!(
+                "roster {} does not contain groups, expected a csv file with a group_name column",
+                self.path.display()
+            );
+        }
+
+        self.read_groups()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::PathBuf};
+
+    use super::{FileRoster, Roster};
+    use crate::lms::StudentFilter;
+    use crate::netid::NetidMapping;
+
+    fn roster(path: PathBuf) -> FileRoster {
+        FileRoster {
+            path,
+            filter: StudentFilter::default(),
+            netids: NetidMapping::default(),
+        }
+    }
+
+    #[test]
+    fn reads_classlist_json() {
+        let roster =
+            roster(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("classlist_example.json"));
+
+        let students = roster.students().unwrap();
+        assert_eq!(students.len(), 1);
+        assert_eq!(students[0].student.netid, "jdoe");
+        assert!(roster.groups().is_err());
+    }
+
+    #[test]
+    fn reads_csv_files() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let students = dir.path().join("students.csv");
+        fs::write(
+            &students,
+            "netid,student_number,email,section\njdoe,12345,J.Doe@tudelft.nl,Lab A\nasmith,,A.Smith@tudelft.nl,\n",
+        )
+        .unwrap();
+        let mut lab_a = roster(students.clone());
+        lab_a.filter.sections = vec!["Lab A".to_string()];
+        assert_eq!(lab_a.students().unwrap().len(), 1);
+        lab_a.filter.enrolled_after = Some(chrono::Utc::now());
+        assert!(lab_a.students().is_err());
+
+        let students = roster(students).students().unwrap();
+        assert_eq!(students.len(), 2);
+        assert_eq!(students[0].sections, vec!["Lab A"]);
+        assert_eq!(students[1].student.student_number, None);
+
+        let groups = dir.path().join("groups.csv");
+        fs::write(
+            &groups,
+            "group_name,netid,email\nGroup 01,jdoe,J.Doe@tudelft.nl\nGroup 01,asmith,A.Smith@tudelft.nl\nGroup 02,bjones,B.Jones@tudelft.nl\n",
+        )
+        .unwrap();
+        let roster = roster(groups);
+        let mut groups = roster.groups().unwrap();
+        groups.sort();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].name, "Group01");
+        assert_eq!(groups[0].members.len(), 2);
+        assert_eq!(roster.students().unwrap().len(), 3);
+    }
+}